@@ -7,8 +7,8 @@ use itertools::Itertools;
 use petgraph::visit::EdgeRef;
 use petgraph::{algo::connected_components, prelude::UnGraph};
 use petgraph::{Graph, Undirected};
-use rand::{distributions::Uniform, prelude::Distribution};
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 #[derive(Debug, Parser)]
 struct Args {
@@ -47,12 +47,20 @@ struct Args {
     /// Max train per station.
     #[clap(long)]
     depot_capacity: Option<usize>,
+
+    /// Seed of random choices, to generate the same input again. A random one by default, printed to stderr.
+    #[clap(long)]
+    seed: Option<u64>,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let graph = generate_graph(args.stations, args.edges, args.max_edge_weight);
+    let seed = args.seed.unwrap_or_else(rand::random);
+    eprintln!("Seed: {seed}");
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let graph = generate_graph(args.stations, args.edges, args.max_edge_weight, &mut rng);
 
     let stations = graph
         .node_indices()
@@ -76,6 +84,7 @@ fn main() -> anyhow::Result<()> {
         args.orders,
         args.max_order_weight,
         args.station_capacity,
+        &mut rng,
     );
 
     let trains = generate_trains(
@@ -83,6 +92,7 @@ fn main() -> anyhow::Result<()> {
         args.trains,
         args.max_train_capacity,
         args.depot_capacity,
+        &mut rng,
     );
 
     let mut buffer =
@@ -133,41 +143,35 @@ fn generate_trains(
     number_of_trains: Option<usize>,
     max_train_capacity: u32,
     depot_capacity: Option<usize>,
+    rng: &mut impl Rng,
 ) -> Vec<String> {
-    let mut rng = thread_rng();
     let node_count = graph.node_count();
     let number_of_trains = number_of_trains.unwrap_or_else(|| rng.gen_range(1..node_count));
     let depot_capacity = depot_capacity.unwrap_or(usize::MAX);
 
     let mut depot_load = HashMap::new();
 
-    let dist = Uniform::from(0..node_count);
-    rng.clone()
-        .sample_iter(dist)
-        .map(|mut node| {
-            loop {
+    (0..number_of_trains)
+        .map(|index| {
+            let node = loop {
+                let node = rng.gen_range(0..node_count);
                 let load = depot_load.entry(node).or_insert(0);
                 if (*load + 1) > depot_capacity {
-                    node = dist.sample(&mut rng.clone());
                     continue;
                 }
                 *load += 1;
-                break;
-            }
-            graph
-                .node_indices()
-                .nth(node)
-                .expect("{node} must be valid node index")
-        })
-        .enumerate()
-        .map(|(index, node)| {
+                break graph
+                    .node_indices()
+                    .nth(node)
+                    .expect("{node} must be valid node index");
+            };
+
             let name = train_label(index);
-            let capacity = rng.clone().gen_range(1..max_train_capacity);
+            let capacity = rng.gen_range(1..max_train_capacity);
             let location = station_label(graph, node);
 
             format!("{name},{capacity},{location}")
         })
-        .take(number_of_trains)
         .collect_vec()
 }
 
@@ -175,9 +179,12 @@ fn train_label(index: usize) -> String {
     format!("T{index}")
 }
 
-fn generate_graph(nodes: usize, edges: usize, max_edge_weight: u32) -> UnGraph<usize, u32> {
-    let mut rnd = thread_rng();
-
+fn generate_graph(
+    nodes: usize,
+    edges: usize,
+    max_edge_weight: u32,
+    rng: &mut impl Rng,
+) -> UnGraph<usize, u32> {
     loop {
         let mut graph = UnGraph::new_undirected();
 
@@ -186,9 +193,9 @@ fn generate_graph(nodes: usize, edges: usize, max_edge_weight: u32) -> UnGraph<u
         }
 
         for _ in 0..edges {
-            let from = rnd.gen_range(0..nodes);
-            let to = rnd.gen_range(0..nodes);
-            let weight = rnd.gen_range(1..max_edge_weight);
+            let from = rng.gen_range(0..nodes);
+            let to = rng.gen_range(0..nodes);
+            let weight = rng.gen_range(1..max_edge_weight);
             graph.add_edge(
                 graph
                     .node_indices()
@@ -213,48 +220,42 @@ fn generate_orders(
     number_of_orders: Option<usize>,
     max_order_weight: u32,
     station_capacity: Option<usize>,
+    rng: &mut impl Rng,
 ) -> Vec<String> {
-    let mut rng = thread_rng();
     let node_count = graph.node_count();
     let number_of_orders = number_of_orders.unwrap_or_else(|| rng.gen_range(1..node_count));
     let station_capacity = station_capacity.unwrap_or(usize::MAX);
 
     let mut station_load = HashMap::new();
 
-    let dist = Uniform::from(0..node_count);
-    rng.clone()
-        .sample_iter(dist)
-        .tuples()
-        .map(|(mut src, dst)| {
+    (0..number_of_orders)
+        .map(|index| {
+            let mut src = rng.gen_range(0..node_count);
+            let dst = rng.gen_range(0..node_count);
             loop {
                 let load = station_load.entry(src).or_insert(0);
                 if (*load + 1) > station_capacity {
-                    src = dist.sample(&mut rng.clone());
+                    src = rng.gen_range(0..node_count);
                     continue;
                 }
                 *load += 1;
                 break;
             }
-            (
-                graph
-                    .node_indices()
-                    .nth(src)
-                    .expect("{n} must be valid node index"),
-                graph
-                    .node_indices()
-                    .nth(dst)
-                    .expect("{m} must be valid node index"),
-            )
-        })
-        .enumerate()
-        .map(|(index, (src, dst))| {
+            let src = graph
+                .node_indices()
+                .nth(src)
+                .expect("{n} must be valid node index");
+            let dst = graph
+                .node_indices()
+                .nth(dst)
+                .expect("{m} must be valid node index");
+
             let name = order_label(index);
-            let weight = rng.clone().gen_range(1..max_order_weight);
+            let weight = rng.gen_range(1..max_order_weight);
             let location = station_label(graph, src);
             let destination = station_label(graph, dst);
             format!("{name},{weight},{location},{destination}")
         })
-        .take(number_of_orders)
         .collect_vec()
 }
 
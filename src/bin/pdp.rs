@@ -1,9 +1,157 @@
-use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
 use log::LevelFilter;
-use pickup_delivery_problem::{solve, Input};
+use pickup_delivery_problem::{
+    solve_exact, solve_with, verify, AlgorithmKind, Budget, CoolingSchedule, DistanceBackend,
+    EvolveOrders, Input, Itinerary, Measure, SearchLimits, SimulatedAnnealingAlgorithm, Solution,
+    SolveOptions, SorterKind, WeightedObjective,
+};
 use simplelog::ConfigBuilder;
 
+#[derive(Debug, Parser)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Input file.
+    #[clap(required = true)]
+    input: Option<PathBuf>,
+
+    /// List algorithms, sorters, objective measures and distance backends to choose from.
+    #[clap(long, exclusive = true)]
+    list: bool,
+
+    /// Format of the input file, detected by the file extension if omitted.
+    #[clap(long, value_enum)]
+    input_format: Option<Format>,
+
+    /// Format of the solution.
+    #[clap(long, value_enum, default_value = "text")]
+    output_format: Format,
+
+    /// How to calculate distances between stations, Dijkstra scales to large networks.
+    #[clap(long, value_enum, default_value = "floyd-warshall")]
+    distances: DistanceBackend,
+
+    /// What to minimize among solutions with the fewest late orders: makespan, train-time,
+    /// delivery-time, lead-time, or a weighted mix of them, e.g. `makespan=1,delivery-time=0.5`.
+    #[clap(long, default_value = "makespan")]
+    objective: WeightedObjective,
+
+    /// Improve the best constructed solution with local search, it takes a while on large inputs
+    /// unless there is a time budget.
+    #[clap(long)]
+    local_search: bool,
+
+    /// Keep orders of sorters for sort-sensitive algorithms, without evolving them further.
+    #[clap(long)]
+    no_evolution: bool,
+
+    /// Construction algorithms to run, comma-separated. All but cheapest-insertion by default,
+    /// `--list` shows what they do.
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "ALGORITHM")]
+    algorithms: Vec<AlgorithmKind>,
+
+    /// Sorters of orders for sort-sensitive algorithms, comma-separated. All by default.
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "SORTER")]
+    sorters: Vec<SorterKind>,
+
+    /// Times the random sorter runs for every sort-sensitive algorithm, unless there is a time budget.
+    #[clap(long, default_value_t = SolveOptions::default().random_iterations, value_name = "ITERATIONS")]
+    random_iterations: usize,
+
+    /// Also run simulated annealing from the greedy solution for this many iterations.
+    #[clap(long, value_name = "ITERATIONS")]
+    anneal: Option<usize>,
+
+    /// How the annealing temperature falls: `geometric=<initial>,<factor>` multiplies it by the factor
+    /// every iteration, `linear=<initial>` lowers it evenly to zero. Temperatures are fractions
+    /// of the cost of the greedy solution.
+    #[clap(long, default_value_t = CoolingSchedule::default(), value_name = "SCHEDULE", requires = "anneal")]
+    cooling: CoolingSchedule,
+
+    /// Seed of random choices, to reproduce a solution. A random one by default, printed to stderr.
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Threads that run algorithms at once, as many as the machine runs by default.
+    #[clap(long)]
+    workers: Option<usize>,
+
+    /// Stop searching after this many seconds and print the best solution found so far.
+    /// Random sorters run until then, instead of a number of times.
+    #[clap(long, value_name = "SECONDS", conflicts_with = "exact")]
+    time_budget: Option<u64>,
+
+    /// Search for an optimal plan with branch-and-bound instead of the heuristics,
+    /// for small inputs or as a baseline to compare heuristics with.
+    #[clap(long)]
+    exact: bool,
+
+    /// The most partial plans the exact search explores.
+    #[clap(long, default_value_t = SearchLimits::default().nodes, requires = "exact")]
+    node_limit: usize,
+
+    /// The longest time in seconds the exact search runs.
+    #[clap(long, default_value_t = SearchLimits::default().time.as_secs(), requires = "exact")]
+    time_limit: u64,
+
+    /// Expand every move into the edges the train travels through, with arrival times.
+    #[clap(long)]
+    paths: bool,
+
+    /// How moves of the solution are ordered.
+    #[clap(long, value_enum, default_value = "time")]
+    sort_by: SortBy,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Checks that a solution is feasible for an input.
+    Verify {
+        /// Input file.
+        input: PathBuf,
+
+        /// Solution file.
+        solution: PathBuf,
+
+        /// Format of the input file, detected by the file extension if omitted.
+        #[clap(long, value_enum)]
+        input_format: Option<Format>,
+
+        /// Format of the solution file, detected by the file extension if omitted.
+        #[clap(long, value_enum)]
+        solution_format: Option<Format>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortBy {
+    /// By departure time.
+    Time,
+    /// By train, and then by departure time.
+    Train,
+}
+
+impl Format {
+    fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::Json,
+            _ => Self::Text,
+        }
+    }
+}
+
 fn main() -> anyhow::Result<()> {
     simplelog::SimpleLogger::init(
         LevelFilter::Info,
@@ -15,16 +163,138 @@ fn main() -> anyhow::Result<()> {
     )
     .unwrap();
 
-    let input = std::env::args()
-        .nth(1)
-        .ok_or_else(|| anyhow!("Missing input file name"))
-        .and_then(|name| std::fs::read_to_string(name).map_err(|e| e.into()))?;
+    let args = Args::parse();
+
+    match args.command {
+        Some(Command::Verify {
+            input,
+            solution,
+            input_format,
+            solution_format,
+        }) => {
+            let input = read_input(&input, input_format)?;
+            let solution = read_solution(&solution, solution_format)?;
 
-    let input = Input::try_from(input.as_str())?;
-    let solution = solve(&input)?;
+            verify(&input, &solution)?;
+            println!("Solution is valid, total time: {}", solution.total_time());
+        }
+        None if args.list => {
+            list("Algorithms", &AlgorithmKind::DEFAULT);
+            list("Sorters", &SorterKind::DEFAULT);
+            list("Objective measures", &[Measure::Makespan]);
+            list("Distance backends", &[DistanceBackend::default()]);
+        }
+        None => {
+            let path = args.input.expect("Input is required");
+            let input = read_input(&path, args.input_format)?;
+            let seed = args.seed.unwrap_or_else(rand::random);
+            eprintln!("Seed: {seed}");
+            let options = SolveOptions {
+                distances: args.distances,
+                objective: args.objective,
+                local_search: args.local_search,
+                evolution: (!args.no_evolution).then(EvolveOrders::default),
+                algorithms: if args.algorithms.is_empty() {
+                    AlgorithmKind::DEFAULT.to_vec()
+                } else {
+                    args.algorithms
+                },
+                sorters: if args.sorters.is_empty() {
+                    SorterKind::DEFAULT.to_vec()
+                } else {
+                    args.sorters
+                },
+                random_iterations: args.random_iterations,
+                workers: args.workers,
+                seed: Some(seed),
+                budget: args
+                    .time_budget
+                    .map(|seconds| Budget::time(Duration::from_secs(seconds)))
+                    .unwrap_or_default(),
+                annealing: args.anneal.map(|iterations| {
+                    SimulatedAnnealingAlgorithm::default()
+                        .with_iterations(iterations)
+                        .with_schedule(args.cooling)
+                }),
+            };
+            let solution = if args.exact {
+                let limits = SearchLimits {
+                    nodes: args.node_limit,
+                    time: Duration::from_secs(args.time_limit),
+                };
+                let outcome = solve_exact(&input, &options, limits)?;
+                if outcome.optimal {
+                    eprintln!("Proved optimal after {} nodes", outcome.nodes);
+                } else {
+                    eprintln!(
+                        "Stopped after {} nodes, the plan may be not optimal",
+                        outcome.nodes
+                    );
+                }
+                outcome.solution
+            } else {
+                solve_with(&input, &options)?
+            }
+            .sort_by_time();
+            let solution = match args.sort_by {
+                SortBy::Time => solution,
+                SortBy::Train => solution.sort_by_train(),
+            };
 
-    println!("{}", solution.sort_by_time().to_string());
-    println!("Total time: {}", solution.total_time());
+            match args.output_format {
+                Format::Text => {
+                    if args.paths {
+                        print!("{}", Itinerary::new(&input, &solution)?);
+                    } else {
+                        print!("{solution}");
+                    }
+                }
+                Format::Json if args.paths => {
+                    println!("{}", Itinerary::new(&input, &solution)?.to_json()?)
+                }
+                Format::Json => println!("{}", solution.to_json()?),
+            }
+        }
+    }
 
     Ok(())
 }
+
+/// Prints values of `T` with their help, marking the ones used by default.
+fn list<T: ValueEnum + PartialEq>(title: &str, defaults: &[T]) {
+    println!("{title}:");
+    for value in T::value_variants() {
+        let possible = value.to_possible_value().expect("Listed value");
+        let default = if defaults.contains(value) {
+            " (default)"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<20}{}{default}",
+            possible.get_name(),
+            possible.get_help().unwrap_or_default()
+        );
+    }
+    println!();
+}
+
+fn read_input(path: &Path, format: Option<Format>) -> anyhow::Result<Input> {
+    let input =
+        std::fs::read_to_string(path).with_context(|| format!("Read input {}", path.display()))?;
+
+    match format.unwrap_or_else(|| Format::detect(path)) {
+        Format::Text => Input::try_from(input.as_str()),
+        Format::Json => Input::from_json(&input),
+    }
+}
+
+fn read_solution(path: &Path, format: Option<Format>) -> anyhow::Result<Solution> {
+    let solution = std::fs::read_to_string(path)
+        .with_context(|| format!("Read solution {}", path.display()))?;
+
+    match format.unwrap_or_else(|| Format::detect(path)) {
+        Format::Text => solution.parse(),
+        Format::Json => Solution::from_json(&solution),
+    }
+}
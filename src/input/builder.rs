@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::ensure;
+
+use crate::model::{Edge, Order, Station, Train};
+
+use super::Input;
+
+/// Collects entries of an input and validates them against the ones added before.
+#[derive(Default)]
+pub struct InputBuilder {
+    stations: Vec<Station>,
+    station_capacities: HashMap<Station, u32>,
+    edges: Vec<Edge>,
+    orders: Vec<Order>,
+    trains: Vec<Train>,
+    station_names: HashSet<Station>,
+    edge_names: HashSet<String>,
+    order_names: HashSet<String>,
+    train_names: HashSet<String>,
+}
+
+impl InputBuilder {
+    pub fn add_station(&mut self, station: Station, capacity: Option<u32>) -> anyhow::Result<()> {
+        ensure!(
+            self.station_names.insert(station.clone()),
+            "Duplicate station `{station}`"
+        );
+        if let Some(capacity) = capacity {
+            ensure!(capacity > 0, "Station `{station}` has zero capacity");
+            self.station_capacities.insert(station.clone(), capacity);
+        }
+        self.stations.push(station);
+        Ok(())
+    }
+
+    pub fn add_edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        self.ensure_station_exists(&edge.stations().0)?;
+        self.ensure_station_exists(&edge.stations().1)?;
+        ensure!(
+            edge.distance() > 0,
+            "Edge `{}` has zero distance",
+            edge.name()
+        );
+        ensure!(
+            edge.capacity() != Some(0),
+            "Edge `{}` has zero capacity",
+            edge.name()
+        );
+        ensure!(
+            self.edge_names.insert(edge.name().to_owned()),
+            "Duplicate edge `{}`",
+            edge.name()
+        );
+        self.edges.push(edge);
+        Ok(())
+    }
+
+    pub fn add_order(&mut self, order: Order) -> anyhow::Result<()> {
+        self.ensure_station_exists(&order.location())?;
+        self.ensure_station_exists(&order.destination())?;
+        if let Some(latest) = order.latest_delivery() {
+            ensure!(
+                latest >= order.earliest_pickup(),
+                "Order `{}` latest delivery {latest} is before its earliest pickup {}",
+                order.name(),
+                order.earliest_pickup()
+            );
+        }
+        ensure!(
+            self.order_names.insert(order.name().to_owned()),
+            "Duplicate order `{}`",
+            order.name()
+        );
+        self.orders.push(order);
+        Ok(())
+    }
+
+    pub fn add_train(&mut self, train: Train) -> anyhow::Result<()> {
+        self.ensure_station_exists(train.location())?;
+        if let Some(end_station) = train.end_station() {
+            self.ensure_station_exists(end_station)?;
+        }
+        ensure!(
+            train.speed().is_finite() && train.speed() > 0.0,
+            "Train `{}` has non-positive speed {}",
+            train.name(),
+            train.speed()
+        );
+        ensure!(
+            self.train_names.insert(train.name().to_owned()),
+            "Duplicate train `{}`",
+            train.name()
+        );
+        self.trains.push(train);
+        Ok(())
+    }
+
+    fn ensure_station_exists(&self, station: &Station) -> anyhow::Result<()> {
+        ensure!(
+            self.station_names.contains(station),
+            "Unknown station `{station}`"
+        );
+        Ok(())
+    }
+
+    pub fn build(self) -> anyhow::Result<Input> {
+        ensure!(self.stations.len() > 1, "There must be an edge (N1,N2)");
+        ensure!(!self.edges.is_empty(), "There must be an edge (N1,N2)");
+        ensure!(!self.trains.is_empty(), "There should be a train");
+
+        for station in &self.stations {
+            let Some(&capacity) = self.station_capacities.get(station) else {
+                continue;
+            };
+            let trains = self
+                .trains
+                .iter()
+                .filter(|t| t.location() == station)
+                .count();
+            ensure!(
+                trains <= capacity as usize,
+                "Station `{station}` has {trains} trains, but its capacity is {capacity}"
+            );
+        }
+
+        Ok(Input {
+            stations: self.stations,
+            station_capacities: self.station_capacities,
+            edges: self.edges,
+            orders: self.orders,
+            trains: self.trains,
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::builder::InputBuilder;
+use super::Input;
+use crate::model::{Edge, Order, Station, Train};
+
+/// JSON representation of [`Input`], stations are referred by name.
+#[derive(Debug, Serialize, Deserialize)]
+struct InputData {
+    stations: Vec<StationData>,
+    edges: Vec<EdgeData>,
+    orders: Vec<OrderData>,
+    trains: Vec<TrainData>,
+}
+
+/// A station is either a name, or an object with a name and a capacity.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StationData {
+    Name(String),
+    WithCapacity { name: String, capacity: u32 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EdgeData {
+    name: String,
+    from: String,
+    to: String,
+    distance: u32,
+    #[serde(default)]
+    directed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    capacity: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OrderData {
+    name: String,
+    weight: u32,
+    location: String,
+    destination: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    earliest_pickup: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest_delivery: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    handling_time: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrainData {
+    name: String,
+    capacity: u32,
+    location: String,
+    #[serde(default = "default_speed", skip_serializing_if = "is_default_speed")]
+    speed: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end_station: Option<String>,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+fn is_default_speed(speed: &f64) -> bool {
+    *speed == default_speed()
+}
+
+impl Input {
+    /// Parses and validates an input in JSON format.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let data: InputData = serde_json::from_str(json).context("Parse JSON input")?;
+
+        let mut builder = InputBuilder::default();
+
+        for (index, station) in data.stations.iter().enumerate() {
+            let (name, capacity) = match station {
+                StationData::Name(name) => (name, None),
+                StationData::WithCapacity { name, capacity } => (name, Some(*capacity)),
+            };
+            builder
+                .add_station(name.as_str().into(), capacity)
+                .with_context(|| format!("Parse stations[{index}]"))?;
+        }
+
+        for (index, edge) in data.edges.iter().enumerate() {
+            builder
+                .add_edge(
+                    Edge::from((
+                        edge.name.as_str(),
+                        edge.from.as_str(),
+                        edge.to.as_str(),
+                        edge.distance,
+                        edge.directed,
+                    ))
+                    .with_capacity(edge.capacity),
+                )
+                .with_context(|| format!("Parse edges[{index}]"))?;
+        }
+
+        for (index, order) in data.orders.iter().enumerate() {
+            builder
+                .add_order(
+                    Order::from((
+                        order.name.as_str(),
+                        order.weight,
+                        order.location.as_str(),
+                        order.destination.as_str(),
+                    ))
+                    .with_time_window(
+                        order.earliest_pickup.unwrap_or_default(),
+                        order.latest_delivery,
+                    )
+                    .with_handling_time(order.handling_time.unwrap_or_default()),
+                )
+                .with_context(|| format!("Parse orders[{index}]"))?;
+        }
+
+        for (index, train) in data.trains.iter().enumerate() {
+            builder
+                .add_train(
+                    Train::from((train.name.as_str(), train.capacity, train.location.as_str()))
+                        .with_speed(train.speed)
+                        .with_end_station(train.end_station.as_deref().map(Station::from)),
+                )
+                .with_context(|| format!("Parse trains[{index}]"))?;
+        }
+
+        builder.build()
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        let data = InputData {
+            stations: self
+                .stations
+                .iter()
+                .map(|s| match self.station_capacity(s) {
+                    None => StationData::Name(s.name().to_owned()),
+                    Some(capacity) => StationData::WithCapacity {
+                        name: s.name().to_owned(),
+                        capacity,
+                    },
+                })
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|e| EdgeData {
+                    name: e.name().to_owned(),
+                    from: e.stations().0.name().to_owned(),
+                    to: e.stations().1.name().to_owned(),
+                    distance: e.distance(),
+                    directed: e.directed(),
+                    capacity: e.capacity(),
+                })
+                .collect(),
+            orders: self
+                .orders
+                .iter()
+                .map(|o| OrderData {
+                    name: o.name().to_owned(),
+                    weight: o.weight(),
+                    location: o.location().name().to_owned(),
+                    destination: o.destination().name().to_owned(),
+                    earliest_pickup: Some(o.earliest_pickup()).filter(|&time| time > 0),
+                    latest_delivery: o.latest_delivery(),
+                    handling_time: Some(o.handling_time()).filter(|&time| time > 0),
+                })
+                .collect(),
+            trains: self
+                .trains
+                .iter()
+                .map(|t| TrainData {
+                    name: t.name().to_owned(),
+                    capacity: t.capacity(),
+                    location: t.location().name().to_owned(),
+                    speed: t.speed(),
+                    end_station: t.end_station().map(|s| s.name().to_owned()),
+                })
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&data).context("Serialize input to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Input;
+
+    static SIMPLE_INPUT: &str = include_str!("../data/simple.txt");
+
+    #[test]
+    fn test_json_round_trip() {
+        for data in [
+            SIMPLE_INPUT,
+            include_str!("../data/time_windows.txt"),
+            include_str!("../data/service_times.txt"),
+            include_str!("../data/home_depot.txt"),
+            include_str!("../data/speed.txt"),
+            include_str!("../data/single_track.txt"),
+            include_str!("../data/station_capacity.txt"),
+        ] {
+            let input = Input::try_from(data).expect("Parse input");
+            let json = input.to_json().expect("Serialize to JSON");
+            let sut = Input::from_json(&json).expect("Parse JSON input");
+
+            assert_eq!(sut.stations, input.stations);
+            assert_eq!(sut.station_capacities, input.station_capacities);
+            assert_eq!(sut.edges, input.edges);
+            assert_eq!(sut.orders, input.orders);
+            assert_eq!(sut.trains, input.trains);
+        }
+    }
+
+    #[test]
+    fn test_parse_json_input() {
+        let sut = Input::from_json(indoc::indoc! {r#"
+            {
+                "stations": ["A", "B"],
+                "edges": [{"name": "E1", "from": "A", "to": "B", "distance": 30}],
+                "orders": [{"name": "K1", "weight": 5, "location": "A", "destination": "B"}],
+                "trains": [{"name": "Q1", "capacity": 6, "location": "B"}]
+            }
+        "#})
+        .expect("Parse JSON input");
+
+        assert_eq!(sut.orders, [("K1", 5, "A", "B").into()]);
+        assert_eq!(sut.trains, [("Q1", 6, "B").into()]);
+    }
+
+    #[test]
+    fn test_reject_invalid_json_input() {
+        let error = Input::from_json(indoc::indoc! {r#"
+            {
+                "stations": ["A", "B"],
+                "edges": [{"name": "E1", "from": "A", "to": "B", "distance": 30}],
+                "orders": [{"name": "K1", "weight": 5, "location": "A", "destination": "C"}],
+                "trains": [{"name": "Q1", "capacity": 6, "location": "B"}]
+            }
+        "#})
+        .expect_err("Invalid input");
+
+        assert_eq!(format!("{error:#}"), "Parse orders[0]: Unknown station `C`");
+    }
+}
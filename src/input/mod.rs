@@ -0,0 +1,39 @@
+mod builder;
+mod json;
+mod text;
+
+use std::collections::HashMap;
+
+use crate::model::{Edge, Order, Station, Train};
+
+#[derive(Debug)]
+pub struct Input {
+    stations: Vec<Station>,
+    station_capacities: HashMap<Station, u32>,
+    edges: Vec<Edge>,
+    orders: Vec<Order>,
+    trains: Vec<Train>,
+}
+
+impl Input {
+    pub fn stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Maximum number of trains at a station at once, unlimited if `None`.
+    pub fn station_capacity(&self, station: &Station) -> Option<u32> {
+        self.station_capacities.get(station).copied()
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn trains(&self) -> &[Train] {
+        &self.trains
+    }
+
+    pub fn orders(&self) -> &[Order] {
+        &self.orders
+    }
+}
@@ -0,0 +1,507 @@
+use std::iter::{Enumerate, Peekable};
+use std::str::Lines;
+
+use anyhow::{anyhow, bail, ensure, Context};
+
+use super::builder::InputBuilder;
+use super::Input;
+use crate::model::{Edge, Order, Train};
+
+impl TryFrom<&str> for Input {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let mut lines = InputLines::new(input);
+        let mut builder = InputBuilder::default();
+
+        let number_of_stations = lines.count("stations")?;
+
+        for _ in 0..number_of_stations {
+            lines.entry("stations", |line| {
+                let [name, capacity] = fields(line, 1)?;
+                let capacity = optional_number(capacity, "capacity")?;
+                builder.add_station(name.into(), capacity)
+            })?;
+        }
+
+        let number_of_edges = lines.count("edges")?;
+
+        for _ in 0..number_of_edges {
+            lines.entry("edges", |line| {
+                let [name, from, to, distance, direction, capacity] = fields(line, 4)?;
+                let distance = number(distance, "distance")?;
+                let directed = direction_marker(direction)?;
+                let capacity = optional_number(capacity, "capacity")?;
+                builder.add_edge(
+                    Edge::from((name, from, to, distance, directed)).with_capacity(capacity),
+                )
+            })?;
+        }
+
+        let number_of_orders = lines.count("orders")?;
+
+        for _ in 0..number_of_orders {
+            lines.entry("orders", |line| {
+                let [name, weight, from, to, earliest, latest, handling] = fields(line, 4)?;
+                let weight = number(weight, "weight")?;
+                let earliest = optional_number(earliest, "earliest pickup")?;
+                let latest = optional_number(latest, "latest delivery")?;
+                let handling = optional_number(handling, "handling time")?;
+                builder.add_order(
+                    Order::from((name, weight, from, to))
+                        .with_time_window(earliest.unwrap_or_default(), latest)
+                        .with_handling_time(handling.unwrap_or_default()),
+                )
+            })?;
+        }
+
+        let number_of_trains = lines.count("trains")?;
+
+        for _ in 0..number_of_trains {
+            lines.entry("trains", |line| {
+                let [name, capacity, location, speed, end_station] = fields(line, 3)?;
+                let capacity = number(capacity, "capacity")?;
+                let speed = optional_speed(speed)?;
+                let end_station = (!end_station.is_empty()).then(|| end_station.into());
+                builder.add_train(
+                    Train::from((name, capacity, location))
+                        .with_speed(speed)
+                        .with_end_station(end_station),
+                )
+            })?;
+        }
+
+        lines.end()?;
+
+        builder.build()
+    }
+}
+
+/// Line iterator that keeps track of line numbers for diagnostics.
+struct InputLines<'i> {
+    lines: Peekable<Enumerate<Lines<'i>>>,
+}
+
+impl<'i> InputLines<'i> {
+    fn new(input: &'i str) -> Self {
+        Self {
+            lines: input.lines().enumerate().peekable(),
+        }
+    }
+
+    fn skip_empty_lines(&mut self) {
+        while self
+            .lines
+            .next_if(|(_, line)| line.trim().is_empty())
+            .is_some()
+        {}
+    }
+
+    /// Reads the number of entries in a section, skipping empty lines before it.
+    fn count(&mut self, section: &str) -> anyhow::Result<usize> {
+        self.skip_empty_lines();
+
+        let (index, line) = self
+            .lines
+            .next()
+            .ok_or_else(|| anyhow!("No more lines."))
+            .with_context(|| format!("Parse number of {section}"))?;
+
+        line.trim()
+            .parse::<usize>()
+            .map_err(|e| anyhow!("{e}: `{}`", line.trim()))
+            .with_context(|| format!("Parse number of {section}, line {}", index + 1))
+    }
+
+    /// Reads the next entry of a section and passes it to `parse`.
+    /// Errors are annotated with the section and the line number.
+    fn entry<F>(&mut self, section: &str, parse: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&str) -> anyhow::Result<()>,
+    {
+        let (index, line) = self
+            .lines
+            .next()
+            .ok_or_else(|| anyhow!("No more lines."))
+            .with_context(|| format!("Parse {section}"))?;
+
+        let line = line.trim();
+
+        let result = if line.is_empty() {
+            Err(anyhow!("Missing entry, found an empty line"))
+        } else {
+            parse(line)
+        };
+
+        result.with_context(|| format!("Parse {section}, line {}", index + 1))
+    }
+
+    /// Ensures there is nothing but empty lines left.
+    fn end(&mut self) -> anyhow::Result<()> {
+        self.skip_empty_lines();
+
+        if let Some((index, line)) = self.lines.next() {
+            bail!(
+                "Unexpected content at line {}: `{}`",
+                index + 1,
+                line.trim()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits an entry into `N` trimmed comma-separated fields.
+/// The first `required` fields must not be empty, the rest are optional and empty if missing.
+fn fields<const N: usize>(line: &str, required: usize) -> anyhow::Result<[&str; N]> {
+    let mut parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+
+    if required == N {
+        ensure!(
+            parts.len() == N,
+            "Expected {N} comma-separated fields, found {}",
+            parts.len()
+        );
+    } else {
+        ensure!(
+            (required..=N).contains(&parts.len()),
+            "Expected {required} to {N} comma-separated fields, found {}",
+            parts.len()
+        );
+    }
+
+    if let Some(position) = parts.iter().take(required).position(|part| part.is_empty()) {
+        bail!("Field {} is empty", position + 1);
+    }
+
+    parts.resize(N, "");
+
+    Ok(parts.try_into().expect("Number of fields is checked"))
+}
+
+fn number(field: &str, what: &str) -> anyhow::Result<u32> {
+    field
+        .parse::<u32>()
+        .map_err(|e| anyhow!("Invalid {what} `{field}`: {e}"))
+}
+
+fn optional_number(field: &str, what: &str) -> anyhow::Result<Option<u32>> {
+    if field.is_empty() {
+        Ok(None)
+    } else {
+        number(field, what).map(Some)
+    }
+}
+
+/// Trains have speed 1 unless given.
+fn optional_speed(field: &str) -> anyhow::Result<f64> {
+    if field.is_empty() {
+        Ok(1.0)
+    } else {
+        field
+            .parse::<f64>()
+            .map_err(|e| anyhow!("Invalid speed `{field}`: {e}"))
+    }
+}
+
+/// Edges are two-way unless marked with `->`, an explicit `<->` is allowed too.
+fn direction_marker(field: &str) -> anyhow::Result<bool> {
+    match field {
+        "" | "<->" => Ok(false),
+        "->" => Ok(true),
+        _ => bail!("Invalid direction `{field}`, expected `->` or `<->`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Edge, Order, Station, Train};
+    use crate::Input;
+
+    static SIMPLE_INPUT: &str = include_str!("../data/simple.txt");
+
+    #[test]
+    fn test_parse_simple_input() {
+        let sut = Input::try_from(SIMPLE_INPUT).expect("Parse simple input");
+
+        assert_eq!(
+            sut.stations,
+            ["A", "B", "C"]
+                .into_iter()
+                .map(Station::from)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            sut.edges,
+            [("E1", "A", "B", 30), ("E2", "B", "C", 10)]
+                .into_iter()
+                .map(Edge::from)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            sut.orders,
+            [("K1", 5, "A", "C")]
+                .into_iter()
+                .map(Order::from)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            sut.trains,
+            [("Q1", 6, "B")]
+                .into_iter()
+                .map(Train::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_data_files() {
+        for input in [
+            include_str!("../data/generated.extralarge.1.txt"),
+            include_str!("../data/generated.extralarge.2.txt"),
+            include_str!("../data/generated.large.1.txt"),
+            include_str!("../data/generated.large.2.txt"),
+            include_str!("../data/generated.small.1.txt"),
+            include_str!("../data/generated.small.multiload.txt"),
+            include_str!("../data/generated.small.overload.txt"),
+        ] {
+            Input::try_from(input).expect("Parse data file");
+        }
+    }
+
+    fn parse_error(input: &str) -> String {
+        format!("{:#}", Input::try_from(input).expect_err("Invalid input"))
+    }
+
+    #[test]
+    fn test_reject_unknown_stations() {
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,C,10\n\n0\n\n1\nQ1,1,A\n"),
+            "Parse edges, line 6: Unknown station `C`"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n1\nK1,1,D,B\n\n1\nQ1,1,A\n"),
+            "Parse orders, line 9: Unknown station `D`"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n1\nQ1,1,Z\n"),
+            "Parse trains, line 11: Unknown station `Z`"
+        );
+    }
+
+    #[test]
+    fn test_reject_duplicate_names() {
+        assert_eq!(
+            parse_error("2\nA\nA\n"),
+            "Parse stations, line 3: Duplicate station `A`"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n2\nK1,1,A,B\nK1,2,B,A\n\n1\nQ1,1,A\n"),
+            "Parse orders, line 10: Duplicate order `K1`"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n2\nQ1,1,A\nQ1,1,B\n"),
+            "Parse trains, line 12: Duplicate train `Q1`"
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_edges() {
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,0\n"),
+            "Parse edges, line 6: Edge `E1` has zero distance"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B\n"),
+            "Parse edges, line 6: Expected 4 to 6 comma-separated fields, found 3"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10,>\n"),
+            "Parse edges, line 6: Invalid direction `>`, expected `->` or `<->`"
+        );
+    }
+
+    #[test]
+    fn test_parse_edge_direction() {
+        let sut = Input::try_from(
+            "2\nA\nB\n\n3\nE1,A,B,10\nE2,A,B,20,->\nE3,B,A,30,<->\n\n0\n\n1\nQ1,1,A\n",
+        )
+        .expect("Parse directed edges");
+
+        assert_eq!(
+            sut.edges,
+            [
+                ("E1", "A", "B", 10, false),
+                ("E2", "A", "B", 20, true),
+                ("E3", "B", "A", 30, false)
+            ]
+            .into_iter()
+            .map(Edge::from)
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_station_capacity() {
+        let sut = Input::try_from("2\nA,2\nB\n\n1\nE1,A,B,10\n\n0\n\n2\nQ1,1,A\nQ2,1,A\n")
+            .expect("Parse station capacity");
+
+        assert_eq!(sut.station_capacity(&"A".into()), Some(2));
+        assert_eq!(sut.station_capacity(&"B".into()), None);
+
+        assert_eq!(
+            parse_error("2\nA,0\nB\n"),
+            "Parse stations, line 2: Station `A` has zero capacity"
+        );
+        assert_eq!(
+            parse_error("2\nA,1\nB\n\n1\nE1,A,B,10\n\n0\n\n2\nQ1,1,A\nQ2,1,A\n"),
+            "Station `A` has 2 trains, but its capacity is 1"
+        );
+    }
+
+    #[test]
+    fn test_parse_edge_capacity() {
+        let sut = Input::try_from("2\nA\nB\n\n2\nE1,A,B,10,,1\nE2,A,B,20,->,3\n\n0\n\n1\nQ1,1,A\n")
+            .expect("Parse edge capacity");
+
+        assert_eq!(
+            sut.edges,
+            [
+                Edge::from(("E1", "A", "B", 10)).with_capacity(Some(1)),
+                Edge::from(("E2", "A", "B", 20, true)).with_capacity(Some(3)),
+            ]
+        );
+
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10,,0\n"),
+            "Parse edges, line 6: Edge `E1` has zero capacity"
+        );
+    }
+
+    #[test]
+    fn test_parse_order_time_windows() {
+        let sut = Input::try_from(
+            "2\nA\nB\n\n1\nE1,A,B,10\n\n3\nK1,1,A,B\nK2,1,A,B,20,50\nK3,1,B,A,,40\n\n1\nQ1,1,A\n",
+        )
+        .expect("Parse time windows");
+
+        assert_eq!(
+            sut.orders,
+            [
+                Order::from(("K1", 1, "A", "B")),
+                Order::from(("K2", 1, "A", "B")).with_time_window(20, Some(50)),
+                Order::from(("K3", 1, "B", "A")).with_time_window(0, Some(40)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_order_handling_time() {
+        let sut = Input::try_from(
+            "2\nA\nB\n\n1\nE1,A,B,10\n\n2\nK1,1,A,B,,,5\nK2,1,A,B,20,50,3\n\n1\nQ1,1,A\n",
+        )
+        .expect("Parse handling time");
+
+        assert_eq!(
+            sut.orders,
+            [
+                Order::from(("K1", 1, "A", "B")).with_handling_time(5),
+                Order::from(("K2", 1, "A", "B"))
+                    .with_time_window(20, Some(50))
+                    .with_handling_time(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_time_windows() {
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n1\nK1,1,A,B,50,20\n"),
+            "Parse orders, line 9: Order `K1` latest delivery 20 is before its earliest pickup 50"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n1\nK1,1,A,B,,soon\n"),
+            "Parse orders, line 9: Invalid latest delivery `soon`: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_parse_train_speed() {
+        let sut = Input::try_from("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n2\nQ1,1,A\nQ2,1,B,2.5\n")
+            .expect("Parse train speed");
+
+        assert_eq!(
+            sut.trains,
+            [
+                Train::from(("Q1", 1, "A")),
+                Train::from(("Q2", 1, "B")).with_speed(2.5),
+            ]
+        );
+
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n1\nQ1,1,A,0\n"),
+            "Parse trains, line 11: Train `Q1` has non-positive speed 0"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n1\nQ1,1,A,fast\n"),
+            "Parse trains, line 11: Invalid speed `fast`: invalid float literal"
+        );
+    }
+
+    #[test]
+    fn test_parse_train_end_station() {
+        let sut = Input::try_from("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n2\nQ1,1,A,,A\nQ2,1,B,2,A\n")
+            .expect("Parse train end station");
+
+        assert_eq!(
+            sut.trains,
+            [
+                Train::from(("Q1", 1, "A")).with_end_station(Some("A".into())),
+                Train::from(("Q2", 1, "B"))
+                    .with_speed(2.0)
+                    .with_end_station(Some("A".into())),
+            ]
+        );
+
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n1\nQ1,1,A,,X\n"),
+            "Parse trains, line 11: Unknown station `X`"
+        );
+    }
+
+    #[test]
+    fn test_reject_non_numeric_values() {
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,ten\n"),
+            "Parse edges, line 6: Invalid distance `ten`: invalid digit found in string"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n1\nK1,-5,A,B\n"),
+            "Parse orders, line 9: Invalid weight `-5`: invalid digit found in string"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\nmany\n"),
+            "Parse number of edges, line 5: invalid digit found in string: `many`"
+        );
+    }
+
+    #[test]
+    fn test_reject_truncated_sections() {
+        assert_eq!(
+            parse_error("3\nA\nB\n\n1\nE1,A,B,10\n"),
+            "Parse stations, line 4: Missing entry, found an empty line"
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n1\nK1,1,A,B\n"),
+            "Parse number of trains: No more lines."
+        );
+        assert_eq!(
+            parse_error("2\nA\nB\n\n1\nE1,A,B,10\n\n0\n\n1\nQ1,1,A\nQ2,1,B\n"),
+            "Unexpected content at line 12: `Q2,1,B`"
+        );
+    }
+}
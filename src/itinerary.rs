@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+
+use crate::model::Station;
+use crate::network::{DistanceBackend, Network};
+use crate::output::{write_summary, Move};
+use crate::{Input, Solution};
+
+/// A solution where every move is expanded into the edges the train travels through.
+#[derive(Debug, Serialize)]
+pub struct Itinerary {
+    moves: Vec<ExpandedMove>,
+    total_time: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unserviceable: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    late: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExpandedMove {
+    #[serde(flatten)]
+    r#move: Move,
+    path: Vec<Leg>,
+}
+
+/// A single edge of a move, with the time the train arrives to the end of it.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Leg {
+    edge: String,
+    from: String,
+    to: String,
+    arrival: u32,
+}
+
+impl Itinerary {
+    /// Expands moves of `solution` into shortest paths over edges of `input`.
+    pub fn new(input: &Input, solution: &Solution) -> anyhow::Result<Self> {
+        let network = Network::new(input, DistanceBackend::Dijkstra);
+        let trains = input
+            .trains()
+            .iter()
+            .map(|t| (t.name(), t))
+            .collect::<HashMap<_, _>>();
+
+        let moves = solution
+            .moves()
+            .iter()
+            .map(|m| {
+                let path = network
+                    .path(&m.from().into(), &m.to().into())
+                    .ok_or_else(|| anyhow!("No path from {} to {}", m.from(), m.to()))?;
+
+                let train = trains
+                    .get(m.train())
+                    .ok_or_else(|| anyhow!("Unknown train `{}`", m.train()))?;
+
+                let mut distance = 0;
+                let path = path
+                    .into_iter()
+                    .map(|(from, to, edge)| {
+                        distance += edge.distance();
+                        Leg {
+                            edge: edge.name().to_owned(),
+                            from: from.name().to_owned(),
+                            to: to.name().to_owned(),
+                            arrival: m.time() + train.travel_time(distance),
+                        }
+                    })
+                    .collect();
+
+                Ok(ExpandedMove {
+                    r#move: m.clone(),
+                    path,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            moves,
+            total_time: solution.total_time(),
+            unserviceable: solution.unserviceable().to_vec(),
+            late: solution.late().to_vec(),
+        })
+    }
+
+    pub fn moves(&self) -> &[ExpandedMove] {
+        &self.moves
+    }
+
+    pub fn total_time(&self) -> u32 {
+        self.total_time
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string_pretty(self).context("Serialize itinerary to JSON")
+    }
+}
+
+impl ExpandedMove {
+    pub fn r#move(&self) -> &Move {
+        &self.r#move
+    }
+
+    pub fn path(&self) -> &[Leg] {
+        &self.path
+    }
+}
+
+impl Leg {
+    pub fn edge(&self) -> &str {
+        &self.edge
+    }
+
+    pub fn from(&self) -> Station {
+        self.from.as_str().into()
+    }
+
+    pub fn to(&self) -> Station {
+        self.to.as_str().into()
+    }
+
+    pub fn arrival(&self) -> u32 {
+        self.arrival
+    }
+}
+
+/// Prints every move followed by its edges, e.g.
+/// ```text
+/// W=30, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+///   E1: A -> B at 60
+///   E2: B -> C at 70
+/// ```
+impl Display for Itinerary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.moves {
+            writeln!(f, "{}", m.r#move)?;
+            for leg in &m.path {
+                writeln!(
+                    f,
+                    "  {}: {} -> {} at {}",
+                    leg.edge, leg.from, leg.to, leg.arrival
+                )?;
+            }
+        }
+        writeln!(f)?;
+        write_summary(f, self.total_time, &self.unserviceable, &self.late)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{solve, Input, Itinerary};
+
+    #[test]
+    fn test_expand_moves() {
+        let input = Input::try_from(include_str!("data/simple.txt")).expect("Test input");
+        let solution = solve(&input).expect("Solve simple input");
+
+        let sut = Itinerary::new(&input, &solution).expect("Expand moves");
+
+        assert_eq!(
+            sut.to_string(),
+            indoc::indoc! {"
+                W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]
+                  E1: B -> A at 30
+                W=30, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+                  E1: A -> B at 60
+                  E2: B -> C at 70
+
+                Total time: 70
+            "}
+        );
+    }
+
+    #[test]
+    fn test_paths_arrive_with_moves() {
+        let input =
+            Input::try_from(include_str!("data/generated.large.1.txt")).expect("Test input");
+        let solution = solve(&input).expect("Solve input");
+
+        let sut = Itinerary::new(&input, &solution).expect("Expand moves");
+
+        let last_arrival = sut
+            .moves()
+            .iter()
+            .filter_map(|m| m.path().last())
+            .map(|leg| leg.arrival())
+            .max()
+            .unwrap_or_default();
+
+        assert_eq!(last_arrival, solution.total_time());
+
+        for m in sut.moves().iter().filter(|m| !m.path().is_empty()) {
+            assert_eq!(m.path()[0].from(), m.r#move().from().into());
+            assert_eq!(m.path().last().unwrap().to(), m.r#move().to().into());
+        }
+    }
+}
@@ -2,59 +2,218 @@
 #![feature(slice_group_by)]
 
 mod input;
+mod itinerary;
 pub mod model;
 mod network;
 mod output;
+mod scheduler;
 mod solver;
+mod timeline;
+mod verifier;
 
-use model::Station;
+use itertools::Itertools;
+use model::{Order, Station};
 use network::Network;
-use solver::{OrderSorter, Solver, SolverResult};
+use solver::{LocalSearch, Rank, Solver, SolverResult};
+use timeline::Timeline;
 
 pub use crate::input::Input;
-pub use crate::output::Solution;
+pub use crate::itinerary::{ExpandedMove, Itinerary, Leg};
+pub use crate::network::DistanceBackend;
+pub use crate::output::{Move, Solution};
+pub use crate::solver::{
+    Algorithm, AlgorithmKind, Budget, CheapestInsertionAlgorithm, CoolingSchedule, EvolveOrders,
+    Measure, SearchLimits, SearchOutcome, SimulatedAnnealingAlgorithm, SorterKind,
+    WeightedObjective,
+};
+pub use crate::verifier::verify;
+
+#[derive(Debug)]
+pub struct SolveOptions {
+    pub distances: DistanceBackend,
+    /// What solutions are ranked by, among those with the fewest late orders.
+    pub objective: WeightedObjective,
+    /// Improve the best solution with local search, off by default as it takes a while on large inputs.
+    pub local_search: bool,
+    /// Construction algorithms to run.
+    pub algorithms: Vec<AlgorithmKind>,
+    /// Sorters of orders for sort-sensitive algorithms.
+    pub sorters: Vec<SorterKind>,
+    /// Also run simulated annealing with these settings, off by default.
+    pub annealing: Option<SimulatedAnnealingAlgorithm<'static>>,
+    /// Evolve orders for sort-sensitive algorithms with these settings, on by default.
+    pub evolution: Option<EvolveOrders>,
+    /// Threads that run algorithms at once, as many as the machine runs if not set.
+    pub workers: Option<usize>,
+    /// Seed of random sorters, evolution and annealing, a random one if not set, which is logged.
+    /// The same seed gives the same solution.
+    pub seed: Option<u64>,
+    /// Times random sorters run for every sort-sensitive algorithm, unless the budget has a deadline.
+    pub random_iterations: usize,
+    /// When searches stop and the best solution found so far is returned, no limit by default.
+    /// A clone of it cancels the solve from another thread.
+    pub budget: Budget,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            distances: DistanceBackend::default(),
+            objective: WeightedObjective::default(),
+            local_search: false,
+            algorithms: AlgorithmKind::DEFAULT.to_vec(),
+            sorters: SorterKind::DEFAULT.to_vec(),
+            annealing: None,
+            evolution: Some(EvolveOrders::default()),
+            workers: None,
+            seed: None,
+            random_iterations: 100,
+            budget: Budget::default(),
+        }
+    }
+}
 
 pub fn solve(input: &Input) -> anyhow::Result<Solution> {
-    let network = Network::from(input);
+    solve_with(input, &SolveOptions::default())
+}
+
+pub fn solve_with(input: &Input, options: &SolveOptions) -> anyhow::Result<Solution> {
+    let network = Network::new(input, options.distances);
 
-    let distance: &dyn Fn(&Station, &Station) -> u32 = &|from, to| network.distance(from, to);
+    let distance: &(dyn Fn(&Station, &Station) -> u32 + Sync) =
+        &|from, to| network.distance(from, to).unwrap_or(solver::UNREACHABLE);
     let trains = input.trains().to_vec();
-    let orders = input.orders().to_vec();
-
-    let sorters: &[&dyn OrderSorter] = &[
-        &solver::DoNotSortOrders,
-        &solver::SortOrdersByDistanceAsc::new(distance),
-        &solver::SortOrdersByDistanceDesc::new(distance),
-        &solver::SortOrdersByNameAsc,
-        &solver::SortOrdersByNameDesc,
-        &solver::SortOrdersRandomly,
-    ];
-
-    let solver = Solver::new(
-        &[
-            &solver::NearestTrainOrderCollectionAlgorithm,
-            &solver::NearestTrainOrderDistributionAlgorithm,
-            &solver::NearestTrainSingleOrderAlgorithm,
-        ],
-        sorters,
-        distance,
-    );
-
-    let solutions = solver.solve(orders, trains)?;
+    let (orders, unserviceable) = partition_orders(input, distance);
+
+    let sorters = options
+        .sorters
+        .iter()
+        .map(|sorter| sorter.sorter(distance))
+        .collect_vec();
+    let sorters = sorters.iter().map(AsRef::as_ref).collect_vec();
+
+    let mut algorithms = options
+        .algorithms
+        .iter()
+        .map(AlgorithmKind::algorithm)
+        .collect_vec();
+    // A random seed is logged, so the solve can be replayed.
+    let seed = options.seed.unwrap_or_else(|| {
+        let seed = rand::random();
+        log::info!("Seed: {seed}");
+        seed
+    });
+    let annealing = options.annealing.clone().map(|annealing| {
+        annealing
+            .with_objective(&options.objective)
+            .with_seed(seed)
+            .with_budget(options.budget.clone())
+    });
+    if let Some(annealing) = &annealing {
+        algorithms.push(annealing);
+    }
+
+    let mut solver = Solver::new(&algorithms, &sorters, distance)
+        .with_objective(&options.objective)
+        .with_seed(seed)
+        .with_budget(options.budget.clone())
+        .with_random_iterations(options.random_iterations);
+    if let Some(evolution) = &options.evolution {
+        solver = solver.with_evolution(evolution);
+    }
+    if let Some(workers) = options.workers {
+        solver = solver.with_workers(workers);
+    }
+
+    let mut solutions = solver.solve(orders.clone(), trains.clone())?;
 
     print_statistic(&solutions);
 
-    solutions
+    // The best construction is improved with neighbourhood moves.
+    if let Some(best) = solutions.first_mut().filter(|_| options.local_search) {
+        best.solution = LocalSearch::new(&orders, &trains, distance)
+            .with_objective(&options.objective)
+            .with_budget(options.budget.clone())
+            .improve(&best.solution);
+    }
+
+    // Trains may conflict on edges and at stations with limited capacity,
+    // so solutions are ranked again once the conflicts are resolved.
+    let mut deadlock = None;
+    let solution = solutions
         .into_iter()
-        .next()
-        .map(|r| r.solution)
-        .ok_or_else(|| anyhow::anyhow!("no solution found"))
+        .filter_map(|r| match scheduler::schedule(input, &network, r.solution) {
+            Ok(solution) => Some(solution),
+            Err(e) => {
+                deadlock.get_or_insert(e);
+                None
+            }
+        })
+        .min_by_key(|solution| {
+            let timeline =
+                Timeline::new(input.orders(), input.trains(), distance, solution.moves());
+            Rank::new(&options.objective, solution, &timeline)
+        });
+
+    match (solution, deadlock) {
+        (Some(solution), _) => Ok(solution.with_unserviceable(unserviceable)),
+        (None, Some(deadlock)) => Err(deadlock),
+        (None, None) => Err(anyhow::anyhow!("no solution found")),
+    }
+}
+
+/// Plans with the branch-and-bound search that minimizes late orders and then the makespan,
+/// regardless of the objective in `options`, and tells whether the plan is proven optimal.
+/// The search stops at `limits`, and returns the best plan found by then.
+/// Optimality does not account for limited edge and station capacities,
+/// so a plan delayed to resolve conflicts is not reported optimal.
+pub fn solve_exact(
+    input: &Input,
+    options: &SolveOptions,
+    limits: SearchLimits,
+) -> anyhow::Result<SearchOutcome> {
+    let network = Network::new(input, options.distances);
+
+    let distance: &dyn Fn(&Station, &Station) -> u32 =
+        &|from, to| network.distance(from, to).unwrap_or(solver::UNREACHABLE);
+    let (orders, unserviceable) = partition_orders(input, distance);
+
+    let outcome =
+        solver::BranchAndBoundAlgorithm::new(limits).search(&orders, input.trains(), distance)?;
+
+    let total_time = outcome.solution.total_time();
+    let solution = scheduler::schedule(input, &network, outcome.solution)?;
+
+    Ok(SearchOutcome {
+        optimal: outcome.optimal && solution.total_time() == total_time,
+        solution: solution.with_unserviceable(unserviceable),
+        nodes: outcome.nodes,
+    })
+}
+
+/// Splits orders into ones that can be planned, and names of ones that no train can reach.
+fn partition_orders(
+    input: &Input,
+    distance: &dyn Fn(&Station, &Station) -> u32,
+) -> (Vec<Order>, Vec<String>) {
+    let (orders, unserviceable): (Vec<_>, Vec<_>) = input
+        .orders()
+        .iter()
+        .cloned()
+        .partition(|order| solver::is_serviceable(distance, input.trains(), order));
+
+    let unserviceable = unserviceable
+        .iter()
+        .map(|order| order.name().to_owned())
+        .collect();
+
+    (orders, unserviceable)
 }
 
 #[cfg(debug_assertions)]
 fn print_statistic(results: &[SolverResult]) {
     for (i, result) in results.iter().enumerate().take(10).rev() {
-        println!(
+        eprintln!(
             "{:>2} {:<50?} / {:<50?}  {:>3}ms {:>3}",
             i + 1,
             result.algorithm,
@@ -71,8 +230,15 @@ fn print_statistic(_results: &[SolverResult]) {}
 
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, Instant};
 
-    use crate::{solve, Input};
+    use crate::network::Network;
+    use crate::solver::{Objective, UNREACHABLE};
+    use crate::timeline::Timeline;
+    use crate::{
+        solve, solve_with, verify, AlgorithmKind, Budget, DistanceBackend, EvolveOrders, Input,
+        Measure, SimulatedAnnealingAlgorithm, Solution, SolveOptions, SorterKind,
+    };
 
     static SIMPLE_INPUT: &str = include_str!("data/simple.txt");
 
@@ -86,7 +252,298 @@ mod tests {
             indoc::indoc! {"
             W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]
             W=30, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+
+            Total time: 70
+        "}
+        );
+    }
+
+    #[test]
+    fn test_solution_json_round_trip() {
+        let input = Input::try_from(SIMPLE_INPUT).expect("Test input");
+        let solution = solve(&input).expect("Solve simple input");
+
+        let json = solution.to_json().expect("Serialize solution");
+        let sut = Solution::from_json(&json).expect("Parse solution");
+
+        assert_eq!(sut.total_time(), solution.total_time());
+        assert_eq!(sut.to_string(), solution.to_string());
+    }
+
+    #[test]
+    fn test_solve_with_dijkstra_distances() {
+        let input =
+            Input::try_from(include_str!("data/generated.large.1.txt")).expect("Test input");
+
+        let expected = solve(&input).expect("Solve with Floyd-Warshall");
+        let solution = solve_with(
+            &input,
+            &SolveOptions {
+                distances: DistanceBackend::Dijkstra,
+                ..Default::default()
+            },
+        )
+        .expect("Solve with Dijkstra");
+
+        assert_eq!(solution.total_time(), expected.total_time());
+    }
+
+    #[test]
+    fn test_solve_with_objective() {
+        let input = Input::try_from(include_str!("data/one_way.txt")).expect("Test input");
+        let delivery_time = |solution: &Solution| {
+            let network = Network::from(&input);
+            let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+            let timeline =
+                Timeline::new(input.orders(), input.trains(), &distance, solution.moves());
+            Measure::DeliveryTime.cost(&timeline)
+        };
+
+        let fastest = solve_with(&input, &SolveOptions::default()).expect("Solve for makespan");
+        let options = SolveOptions {
+            objective: "delivery-time".parse().expect("Objective"),
+            ..Default::default()
+        };
+        let earliest = solve_with(&input, &options).expect("Solve for delivery time");
+
+        verify(&input, &earliest).expect("Valid solution");
+        assert_eq!(fastest.total_time(), 77);
+        assert_eq!(earliest.total_time(), 97);
+        assert!(delivery_time(&earliest) < delivery_time(&fastest));
+    }
+
+    #[test]
+    fn test_solve_with_local_search() {
+        let input = Input::try_from(include_str!("data/one_way.txt")).expect("Test input");
+
+        let constructed = solve(&input).expect("Solve without local search");
+        let improved = solve_with(
+            &input,
+            &SolveOptions {
+                local_search: true,
+                ..Default::default()
+            },
+        )
+        .expect("Solve with local search");
+
+        assert_eq!(constructed.total_time(), 77);
+        assert_eq!(improved.total_time(), 75);
+        verify(&input, &improved).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_annealing() {
+        let input = Input::try_from(include_str!("data/one_way.txt")).expect("Test input");
+        let options = SolveOptions {
+            annealing: Some(SimulatedAnnealingAlgorithm::default().with_iterations(1000)),
+            ..Default::default()
+        };
+        let solution = solve_with(&input, &options).expect("Solve with annealing");
+
+        // Constructions alone reach 77.
+        assert!(solution.total_time() <= 77);
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_within_budget() {
+        let input =
+            Input::try_from(include_str!("data/generated.large.2.txt")).expect("Test input");
+        let options = SolveOptions {
+            local_search: true,
+            annealing: Some(SimulatedAnnealingAlgorithm::default().with_iterations(usize::MAX)),
+            budget: Budget::time(Duration::from_secs(1)),
+            ..Default::default()
+        };
+
+        // The walk would never end, the best solution so far comes back soon after the deadline.
+        let started = Instant::now();
+        let solution = solve_with(&input, &options).expect("Solve within budget");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_extralarge_input_quickly() {
+        let input =
+            Input::try_from(include_str!("data/generated.extralarge.1.txt")).expect("Test input");
+
+        // Local search is off by default, it takes a while on large inputs.
+        let started = Instant::now();
+        let solution = solve(&input).expect("Solve by default");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        verify(&input, &solution).expect("Valid solution");
+
+        // Otherwise it stops at the deadline with the best solution so far.
+        let options = SolveOptions {
+            local_search: true,
+            budget: Budget::time(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let improved = solve_with(&input, &options).expect("Solve with local search");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        verify(&input, &improved).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_seed() {
+        let input =
+            Input::try_from(include_str!("data/generated.large.2.txt")).expect("Test input");
+        let options = SolveOptions {
+            annealing: Some(SimulatedAnnealingAlgorithm::default().with_iterations(1000)),
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        // A seeded solve is replayed exactly.
+        let solution = solve_with(&input, &options).expect("Solve with seed");
+        assert_eq!(solve_with(&input, &options).expect("Solve again"), solution);
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_insertion() {
+        let input = Input::try_from(include_str!("data/one_way.txt")).expect("Test input");
+        let options = SolveOptions {
+            algorithms: vec![AlgorithmKind::CheapestInsertion],
+            ..Default::default()
+        };
+        let solution = solve_with(&input, &options).expect("Solve with insertion");
+
+        // Constructions alone reach 77, a train carrying two orders at once does better.
+        assert_eq!(solution.total_time(), 75);
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_chosen_sorters() {
+        let input =
+            Input::try_from(include_str!("data/generated.large.2.txt")).expect("Test input");
+        let options = SolveOptions {
+            algorithms: vec![AlgorithmKind::SingleOrder],
+            sorters: vec![SorterKind::WeightAsc, SorterKind::WeightDesc],
+            evolution: None,
+            ..Default::default()
+        };
+        let solution = solve_with(&input, &options).expect("Solve with chosen sorters");
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_invalid_evolution() {
+        let input = Input::try_from(SIMPLE_INPUT).expect("Test input");
+        let options = SolveOptions {
+            evolution: Some(EvolveOrders {
+                population: 1,
+                elite: 0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            solve_with(&input, &options)
+                .expect_err("Invalid evolution")
+                .to_string(),
+            "Evolution population must be at least 2, got 1"
+        );
+    }
+
+    #[test]
+    fn test_solve_disconnected_network() {
+        let input = Input::try_from(include_str!("data/disconnected.txt")).expect("Test input");
+
+        for distances in [DistanceBackend::FloydWarshall, DistanceBackend::Dijkstra] {
+            let options = SolveOptions {
+                distances,
+                ..Default::default()
+            };
+            let solution = solve_with(&input, &options).expect("Solve input");
+
+            assert_eq!(solution.unserviceable(), ["K2", "K3"]);
+            verify(&input, &solution).expect("Valid solution");
+        }
+    }
+
+    #[test]
+    fn test_solve_time_windows() {
+        let input = Input::try_from(include_str!("data/time_windows.txt")).expect("Test input");
+        let solution = solve(&input).expect("Solve input").sort_by_time();
+
+        assert!(solution.late().is_empty());
+        assert_eq!(
+            &solution.to_string(),
+            indoc::indoc! {"
+            W=0, T=Q2, N1=D, P1=[K3], N2=B, P2=[K3]
+            W=0, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+            W=30, T=Q2, N1=B, P1=[K2], N2=D, P2=[K2]
+            W=50, T=Q1, N1=C, P1=[K4], N2=A, P2=[K4]
+
+            Total time: 70
+        "}
+        );
+    }
+
+    #[test]
+    fn test_solve_with_service_times() {
+        let input = Input::try_from(include_str!("data/service_times.txt")).expect("Test input");
+        let solution = solve(&input).expect("Solve input").sort_by_time();
+
+        // Q1 is busy loading and unloading K1, so Q2 comes for K2 after delivering K3.
+        assert_eq!(solution.total_time(), 70);
+        assert_eq!(
+            &solution.to_string(),
+            indoc::indoc! {"
+            W=10, T=Q2, N1=C, P1=[K3], N2=B, P2=[K3]
+            W=30, T=Q1, N1=A, P1=[K1], N2=B, P2=[K1]
+            W=30, T=Q2, N1=B, P1=[], N2=A, P2=[]
+            W=45, T=Q2, N1=A, P1=[K2], N2=C, P2=[K2]
+
+            Total time: 70
+        "}
+        );
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_end_stations() {
+        let input = Input::try_from(include_str!("data/home_depot.txt")).expect("Test input");
+        let solution = solve(&input).expect("Solve input").sort_by_time();
+
+        // Q1 is at K1, but Q2 delivers it to its own end station, and Q1 returns to its depot after K2.
+        assert_eq!(solution.total_time(), 40);
+        assert_eq!(
+            &solution.to_string(),
+            indoc::indoc! {"
+            W=0, T=Q2, N1=C, P1=[], N2=B, P2=[]
+            W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]
+            W=10, T=Q2, N1=B, P1=[K1], N2=D, P2=[K1]
+            W=10, T=Q1, N1=A, P1=[K2], N2=C, P2=[K2]
+            W=30, T=Q1, N1=C, P1=[], N2=B, P2=[]
+
+            Total time: 40
+        "}
+        );
+        verify(&input, &solution).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_solve_with_train_speed() {
+        let input = Input::try_from(include_str!("data/speed.txt")).expect("Test input");
+        let solution = solve(&input).expect("Solve input");
+
+        // Q2 is twice further from the order than Q1, but four times faster.
+        assert_eq!(solution.total_time(), 40);
+        assert_eq!(
+            &solution.to_string(),
+            indoc::indoc! {"
+            W=0, T=Q2, N1=A, P1=[], N2=C, P2=[]
+            W=20, T=Q2, N1=C, P1=[K1], N2=A, P2=[K1]
+
+            Total time: 40
         "}
         );
+        verify(&input, &solution).expect("Valid solution");
     }
 }
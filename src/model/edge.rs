@@ -5,17 +5,30 @@ pub struct Edge {
     name: String,
     stations: (Station, Station),
     distance: u32,
+    directed: bool,
+    capacity: Option<u32>,
 }
 
 impl Edge {
-    pub fn new(name: String, from: Station, to: Station, distance: u32) -> Self {
+    pub fn new(name: String, from: Station, to: Station, distance: u32, directed: bool) -> Self {
         Self {
             name,
             stations: (from, to),
             distance,
+            directed,
+            capacity: None,
         }
     }
 
+    /// Limits how many trains can be on the edge at once, in both directions together.
+    pub fn with_capacity(self, capacity: Option<u32>) -> Self {
+        Self { capacity, ..self }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn stations(&self) -> &(Station, Station) {
         &self.stations
     }
@@ -23,6 +36,16 @@ impl Edge {
     pub fn distance(&self) -> u32 {
         self.distance
     }
+
+    /// One-way edge can only be traveled from the first station to the second one.
+    pub fn directed(&self) -> bool {
+        self.directed
+    }
+
+    /// Maximum number of trains on the edge at once, unlimited if `None`.
+    pub fn capacity(&self) -> Option<u32> {
+        self.capacity
+    }
 }
 
 impl From<(&str, &str, &str, u32)> for Edge {
@@ -31,6 +54,17 @@ impl From<(&str, &str, &str, u32)> for Edge {
             name: data.0.to_string(),
             stations: (data.1.into(), data.2.into()),
             distance: data.3,
+            directed: false,
+            capacity: None,
+        }
+    }
+}
+
+impl From<(&str, &str, &str, u32, bool)> for Edge {
+    fn from(data: (&str, &str, &str, u32, bool)) -> Self {
+        Self {
+            directed: data.4,
+            ..(data.0, data.1, data.2, data.3).into()
         }
     }
 }
@@ -6,6 +6,9 @@ pub struct Order {
     weight: u32,
     location: Station,
     destination: Station,
+    earliest_pickup: u32,
+    latest_delivery: Option<u32>,
+    handling_time: u32,
 }
 
 impl Order {
@@ -15,6 +18,19 @@ impl Order {
             weight,
             location,
             destination,
+            earliest_pickup: 0,
+            latest_delivery: None,
+            handling_time: 0,
+        }
+    }
+
+    /// Sets the time window: the order can't be picked up before `earliest_pickup`,
+    /// and should be delivered not later than `latest_delivery`.
+    pub fn with_time_window(self, earliest_pickup: u32, latest_delivery: Option<u32>) -> Self {
+        Self {
+            earliest_pickup,
+            latest_delivery,
+            ..self
         }
     }
 
@@ -34,6 +50,31 @@ impl Order {
         self.destination.clone()
     }
 
+    /// Sets the time to load the order at its location, and the same time to unload it at its destination.
+    pub fn with_handling_time(self, handling_time: u32) -> Self {
+        Self {
+            handling_time,
+            ..self
+        }
+    }
+
+    pub fn handling_time(&self) -> u32 {
+        self.handling_time
+    }
+
+    pub fn earliest_pickup(&self) -> u32 {
+        self.earliest_pickup
+    }
+
+    pub fn latest_delivery(&self) -> Option<u32> {
+        self.latest_delivery
+    }
+
+    /// Whether the order unloaded at `time` misses its latest delivery.
+    pub fn is_late(&self, time: u32) -> bool {
+        matches!(self.latest_delivery, Some(latest) if time > latest)
+    }
+
     pub fn move_to(self, location: &Station) -> Self {
         Self {
             location: location.clone(),
@@ -53,6 +94,9 @@ impl From<(&str, u32, &str, &str)> for Order {
             weight: tuple.1,
             location: tuple.2.into(),
             destination: tuple.3.into(),
+            earliest_pickup: 0,
+            latest_delivery: None,
+            handling_time: 0,
         }
     }
 }
@@ -1,10 +1,12 @@
 use super::Station;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Train {
     name: String,
     capacity: u32,
     location: Station,
+    speed: f64,
+    end_station: Option<Station>,
     traveled_time: u32,
 }
 
@@ -14,10 +16,25 @@ impl Train {
             name,
             capacity,
             location,
+            speed: 1.0,
+            end_station: None,
             traveled_time: 0,
         }
     }
 
+    /// Sets the speed factor, a train with speed 2 travels a distance twice faster.
+    pub fn with_speed(self, speed: f64) -> Self {
+        Self { speed, ..self }
+    }
+
+    /// Sets the station the train has to return to by the end of the plan.
+    pub fn with_end_station(self, end_station: Option<Station>) -> Self {
+        Self {
+            end_station,
+            ..self
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -30,11 +47,35 @@ impl Train {
         &self.location
     }
 
-    pub fn move_to(&mut self, destination: &Station, traveled_time: u32) {
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn end_station(&self) -> Option<&Station> {
+        self.end_station.as_ref()
+    }
+
+    /// Time to travel `distance`, rounded up.
+    pub fn travel_time(&self, distance: u32) -> u32 {
+        (distance as f64 / self.speed).ceil() as u32
+    }
+
+    pub fn move_to(&mut self, destination: &Station, distance: u32) {
         self.location = destination.clone();
-        self.traveled_time += traveled_time;
+        self.traveled_time += self.travel_time(distance);
+    }
+
+    /// Keeps the train busy loading or unloading orders for `handling_time`.
+    pub fn handle(&mut self, handling_time: u32) {
+        self.traveled_time += handling_time;
+    }
+
+    /// Keeps the train at its location until `time`, if it is not there yet.
+    pub fn wait_until(&mut self, time: u32) {
+        self.traveled_time = self.traveled_time.max(time);
     }
 
+    /// Time when the train is available at its location, including waiting and handling orders.
     pub fn traveled_time(&self) -> u32 {
         self.traveled_time
     }
@@ -46,6 +87,8 @@ impl From<(&str, u32, &str)> for Train {
             name: tuple.0.to_string(),
             capacity: tuple.1,
             location: tuple.2.into(),
+            speed: 1.0,
+            end_station: None,
             traveled_time: 0,
         }
     }
@@ -1,71 +1,323 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::sync::OnceLock;
 
 use petgraph::algo::floyd_warshall;
-use petgraph::prelude::UnGraph;
-use petgraph::stable_graph::NodeIndex;
+use petgraph::prelude::DiGraph;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
 
-use crate::model::Station;
+use crate::model::{Edge, Station};
 use crate::Input;
 
+/// How distances between stations are calculated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DistanceBackend {
+    /// Calculates all pairs upfront with Floyd–Warshall, O(n³) time and O(n²) memory.
+    #[default]
+    FloydWarshall,
+    /// Runs Dijkstra from a station when a distance from it is needed the first time,
+    /// and caches the result.
+    Dijkstra,
+}
+
+type AllPairs = HashMap<(NodeIndex, NodeIndex), u32>;
+
+/// Shortest paths from a source to every node, indexed by node index.
+#[derive(Debug)]
+struct ShortestPaths {
+    /// Unreachable nodes are `u32::MAX` like in Floyd–Warshall.
+    distances: Vec<u32>,
+    /// The last edge of the shortest path to a node.
+    predecessors: Vec<Option<EdgeIndex>>,
+}
+
 #[derive(Debug)]
 pub struct Network<'n> {
-    _graph: UnGraph<&'n Station, u32>,
+    graph: DiGraph<&'n Station, &'n Edge>,
     station_to_index: HashMap<&'n Station, NodeIndex>,
-    distances: HashMap<(NodeIndex<u32>, NodeIndex<u32>), u32>,
+    all_pairs: Option<AllPairs>,
+    /// Shortest paths from every node, calculated the first time they are needed.
+    /// Threads only wait for each other to calculate paths from the same node.
+    shortest_paths: Vec<OnceLock<ShortestPaths>>,
 }
 
 impl<'n> Network<'n> {
-    pub fn _distances(&self) -> HashMap<(&Station, &Station), u32> {
-        self.distances
+    pub fn new(input: &'n Input, backend: DistanceBackend) -> Self {
+        let mut graph = DiGraph::new();
+        let node_map = input
+            .stations()
             .iter()
-            .map(|(&(from, to), &distance)| {
-                (
-                    (
-                        *self._graph.node_weight(from).unwrap(),
-                        *self._graph.node_weight(to).unwrap(),
-                    ),
-                    distance,
-                )
+            .map(|s| (s, graph.add_node(s)))
+            .collect::<HashMap<_, _>>();
+
+        // Only the shortest of parallel edges matters.
+        let mut edges = BTreeMap::new();
+        let mut add_edge = |from: &Station, to: &Station, edge: &'n Edge| {
+            let shortest = edges.entry((node_map[from], node_map[to])).or_insert(edge);
+            if edge.distance() < shortest.distance() {
+                *shortest = edge;
+            }
+        };
+
+        input.edges().iter().for_each(|e| {
+            add_edge(&e.stations().0, &e.stations().1, e);
+            if !e.directed() {
+                add_edge(&e.stations().1, &e.stations().0, e);
+            }
+        });
+
+        edges.into_iter().for_each(|((from, to), edge)| {
+            graph.add_edge(from, to, edge);
+        });
+
+        let all_pairs = match backend {
+            DistanceBackend::FloydWarshall => Some(
+                floyd_warshall(&graph, |e| e.weight().distance()).expect("Calcucalte distances"),
+            ),
+            DistanceBackend::Dijkstra => None,
+        };
+
+        Self {
+            shortest_paths: graph.node_indices().map(|_| OnceLock::new()).collect(),
+            graph,
+            station_to_index: node_map,
+            all_pairs,
+        }
+    }
+
+    pub fn _distances(&self) -> HashMap<(&Station, &Station), Option<u32>> {
+        self.graph
+            .node_weights()
+            .flat_map(|&from| {
+                self.graph
+                    .node_weights()
+                    .map(move |&to| ((from, to), self.distance(from, to)))
             })
             .collect()
     }
 
-    // I assume that all nodes are connected.
-    pub fn distance(&self, from: &Station, to: &Station) -> u32 {
-        let from = self.station_to_index.get(from).unwrap();
-        let to = self.station_to_index.get(to).unwrap();
-        *self.distances.get(&(*from, *to)).unwrap()
+    /// Distance of the shortest path from `from` to `to`,
+    /// `None` if `to` can't be reached from `from` or any of them is not in the network.
+    pub fn distance(&self, from: &Station, to: &Station) -> Option<u32> {
+        let from = *self.station_to_index.get(from)?;
+        let to = *self.station_to_index.get(to)?;
+
+        let distance = match &self.all_pairs {
+            Some(distances) => *distances.get(&(from, to))?,
+            None => self.with_shortest_paths(from, |paths| paths.distances[to.index()]),
+        };
+
+        (distance != u32::MAX).then_some(distance)
+    }
+
+    /// Edges of the shortest path from `from` to `to` in travel order,
+    /// each one with the stations it is traveled from and to.
+    pub fn path(
+        &self,
+        from: &Station,
+        to: &Station,
+    ) -> Option<Vec<(&'n Station, &'n Station, &'n Edge)>> {
+        let from = *self.station_to_index.get(from)?;
+        let to = *self.station_to_index.get(to)?;
+
+        self.with_shortest_paths(from, |paths| {
+            let mut path = Vec::new();
+            let mut node = to;
+
+            while node != from {
+                let edge = paths.predecessors[node.index()]?;
+                let (source, target) = self.graph.edge_endpoints(edge)?;
+                path.push((self.graph[source], self.graph[target], self.graph[edge]));
+                node = source;
+            }
+
+            path.reverse();
+            Some(path)
+        })
+    }
+
+    fn with_shortest_paths<T>(&self, source: NodeIndex, f: impl FnOnce(&ShortestPaths) -> T) -> T {
+        f(self.shortest_paths[source.index()].get_or_init(|| self.shortest_paths_from(source)))
+    }
+
+    fn shortest_paths_from(&self, source: NodeIndex) -> ShortestPaths {
+        let mut distances = vec![u32::MAX; self.graph.node_count()];
+        let mut predecessors = vec![None; self.graph.node_count()];
+        let mut queue = BinaryHeap::new();
+
+        distances[source.index()] = 0;
+        queue.push(Reverse((0, source)));
+
+        while let Some(Reverse((distance, node))) = queue.pop() {
+            if distance > distances[node.index()] {
+                continue;
+            }
+
+            for edge in self.graph.edges(node) {
+                let next = edge.target();
+                let candidate = distance + edge.weight().distance();
+
+                if candidate < distances[next.index()] {
+                    distances[next.index()] = candidate;
+                    predecessors[next.index()] = Some(edge.id());
+                    queue.push(Reverse((candidate, next)));
+                }
+            }
+        }
+
+        ShortestPaths {
+            distances,
+            predecessors,
+        }
     }
 }
 
 impl<'n> From<&'n Input> for Network<'n> {
     fn from(input: &'n Input) -> Self {
-        let mut graph = UnGraph::new_undirected();
-        let node_map = input
-            .stations()
-            .iter()
-            .map(|s| (s, graph.add_node(s)))
-            .collect::<HashMap<_, _>>();
+        Self::new(input, DistanceBackend::default())
+    }
+}
 
-        input.edges().iter().for_each(|e| {
-            graph.add_edge(
-                node_map[&e.stations().0],
-                node_map[&e.stations().1],
-                e.distance(),
-            );
-            graph.add_edge(
-                node_map[&e.stations().1],
-                node_map[&e.stations().0],
-                e.distance(),
-            );
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::network::{DistanceBackend, Network};
+    use crate::Input;
+
+    #[test]
+    fn test_directed_distances() {
+        let input = Input::try_from(indoc::indoc! {"
+            3
+            A
+            B
+            C
+
+            3
+            E1,A,B,10,->
+            E2,B,C,20
+            E3,C,A,5,->
+
+            0
+
+            1
+            Q1,1,A
+        "})
+        .expect("Test input");
+
+        for backend in [DistanceBackend::FloydWarshall, DistanceBackend::Dijkstra] {
+            let sut = Network::new(&input, backend);
+
+            assert_eq!(sut.distance(&"A".into(), &"B".into()), Some(10));
+            assert_eq!(sut.distance(&"B".into(), &"A".into()), Some(25));
+            assert_eq!(sut.distance(&"A".into(), &"C".into()), Some(30));
+            assert_eq!(sut.distance(&"C".into(), &"A".into()), Some(5));
+        }
+    }
+
+    #[test]
+    fn test_unreachable_distances() {
+        let input = Input::try_from(include_str!("data/disconnected.txt")).expect("Test input");
+
+        for backend in [DistanceBackend::FloydWarshall, DistanceBackend::Dijkstra] {
+            let sut = Network::new(&input, backend);
+
+            assert_eq!(sut.distance(&"A".into(), &"C".into()), Some(15));
+            assert_eq!(sut.distance(&"A".into(), &"D".into()), None);
+            assert_eq!(sut.distance(&"E".into(), &"B".into()), None);
+            assert_eq!(sut.distance(&"A".into(), &"X".into()), None);
+            assert_eq!(sut.path(&"A".into(), &"D".into()), None);
+        }
+    }
+
+    #[test]
+    fn test_shortest_parallel_edge() {
+        let input = Input::try_from(indoc::indoc! {"
+            2
+            A
+            B
+
+            3
+            E1,A,B,10
+            E2,B,A,3
+            E3,A,B,7
+
+            0
+
+            1
+            Q1,1,A
+        "})
+        .expect("Test input");
+
+        for backend in [DistanceBackend::FloydWarshall, DistanceBackend::Dijkstra] {
+            let sut = Network::new(&input, backend);
+
+            assert_eq!(sut.distance(&"A".into(), &"B".into()), Some(3));
+            assert_eq!(sut.distance(&"B".into(), &"A".into()), Some(3));
+        }
+    }
+
+    #[test]
+    fn test_dijkstra_matches_floyd_warshall() {
+        for data in [
+            include_str!("data/generated.extralarge.1.txt"),
+            include_str!("data/generated.large.1.txt"),
+            include_str!("data/generated.large.2.txt"),
+            include_str!("data/generated.small.1.txt"),
+            include_str!("data/generated.small.multiload.txt"),
+            include_str!("data/one_way.txt"),
+            include_str!("data/disconnected.txt"),
+        ] {
+            let input = Input::try_from(data).expect("Test input");
+
+            let floyd_warshall = Network::new(&input, DistanceBackend::FloydWarshall);
+            let dijkstra = Network::new(&input, DistanceBackend::Dijkstra);
+
+            assert_eq!(dijkstra._distances(), floyd_warshall._distances());
+        }
+    }
+
+    #[test]
+    fn test_dijkstra_from_many_threads() {
+        let input =
+            Input::try_from(include_str!("data/generated.large.1.txt")).expect("Test input");
+        let floyd_warshall = Network::new(&input, DistanceBackend::FloydWarshall);
+        let dijkstra = Network::new(&input, DistanceBackend::Dijkstra);
+
+        // Threads fill the cache at once, every one from all sources.
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for from in input.stations() {
+                        for to in input.stations() {
+                            assert_eq!(
+                                dijkstra.distance(from, to),
+                                floyd_warshall.distance(from, to)
+                            );
+                        }
+                    }
+                });
+            }
         });
+    }
 
-        let distances = floyd_warshall(&graph, |e| *e.weight()).expect("Calcucalte distances");
+    #[test]
+    fn test_shortest_path() {
+        let input = Input::try_from(include_str!("data/one_way.txt")).expect("Test input");
 
-        Self {
-            _graph: graph,
-            station_to_index: node_map,
-            distances,
+        for backend in [DistanceBackend::FloydWarshall, DistanceBackend::Dijkstra] {
+            let sut = Network::new(&input, backend);
+
+            let path = sut
+                .path(&"B".into(), &"A".into())
+                .expect("Path from B to A")
+                .into_iter()
+                .map(|(from, to, edge)| (from.name(), to.name(), edge.name()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(path, [("B", "C", "E2"), ("C", "A", "E3")]);
+            assert_eq!(sut.path(&"A".into(), &"A".into()), Some(vec![]));
         }
     }
 }
@@ -1,6 +1,14 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Characters the text format separates fields and lists with, escaped in names with `\`.
+const RESERVED: [char; 5] = ['\\', ',', '[', ']', '='];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Move {
     time: u32,           // W
     train: String,       // T
@@ -28,22 +36,97 @@ impl Move {
             unload,
         }
     }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    pub fn train(&self) -> &str {
+        &self.train
+    }
+
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    pub fn load(&self) -> &[String] {
+        &self.load
+    }
+
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+
+    pub fn unload(&self) -> &[String] {
+        &self.unload
+    }
+
+    /// The same move departing at `time`.
+    pub fn departing_at(&self, time: u32) -> Self {
+        Self {
+            time,
+            ..self.clone()
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Solution {
     moves: Vec<Move>,
     total_time: u32,
+    /// Orders that no train can pick up or deliver because their stations are unreachable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unserviceable: Vec<String>,
+    /// Orders delivered after their latest delivery time.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    late: Vec<String>,
 }
 
 impl Solution {
     pub fn new(moves: Vec<Move>, total_time: u32) -> Self {
-        Self { moves, total_time }
+        Self {
+            moves,
+            total_time,
+            unserviceable: vec![],
+            late: vec![],
+        }
+    }
+
+    pub fn with_unserviceable(self, unserviceable: Vec<String>) -> Self {
+        Self {
+            unserviceable,
+            ..self
+        }
+    }
+
+    pub fn with_late(self, late: Vec<String>) -> Self {
+        Self { late, ..self }
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
     }
 
     pub fn total_time(&self) -> u32 {
         self.total_time
     }
 
+    pub fn unserviceable(&self) -> &[String] {
+        &self.unserviceable
+    }
+
+    pub fn late(&self) -> &[String] {
+        &self.late
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("Parse JSON solution")
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string_pretty(self).context("Serialize solution to JSON")
+    }
+
     pub fn sort_by_time(&self) -> Self {
         Self {
             moves: self
@@ -53,6 +136,8 @@ impl Solution {
                 .sorted_by_key(|m| m.time)
                 .collect(),
             total_time: self.total_time,
+            unserviceable: self.unserviceable.clone(),
+            late: self.late.clone(),
         }
     }
 
@@ -65,29 +150,336 @@ impl Solution {
                 .sorted_by_key(|m| m.train.clone())
                 .collect(),
             total_time: self.total_time,
+            unserviceable: self.unserviceable.clone(),
+            late: self.late.clone(),
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "W={}, T={}, N1={}, P1=[{}], N2={}, P2=[{}]",
+            self.time,
+            escape(&self.train),
+            escape(&self.from),
+            escape_list(&self.load),
+            escape(&self.to),
+            escape_list(&self.unload),
+        )
+    }
+}
+
+/// Moves, one per line, and the total time, unserviceable and late orders after an empty line.
+/// Reserved characters in names are escaped with `\`, so [`Solution::from_str`] reads it back.
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.moves {
+            writeln!(f, "{m}")?;
+        }
+        writeln!(f)?;
+        write_summary(f, self.total_time, &self.unserviceable, &self.late)
+    }
+}
+
+/// Lines written after moves of a solution or an itinerary.
+pub(crate) fn write_summary(
+    f: &mut std::fmt::Formatter<'_>,
+    total_time: u32,
+    unserviceable: &[String],
+    late: &[String],
+) -> std::fmt::Result {
+    writeln!(f, "Total time: {total_time}")?;
+    if !unserviceable.is_empty() {
+        writeln!(f, "Unserviceable orders: {}", escape_list(unserviceable))?;
+    }
+    if !late.is_empty() {
+        writeln!(f, "Late orders: {}", escape_list(late))?;
+    }
+    Ok(())
+}
+
+/// Parses a solution in the format of its [`Display`], the exact inverse of it.
+/// Unserviceable and late orders are taken from `Unserviceable orders: ..`
+/// and `Late orders: ..` lines.
+impl FromStr for Solution {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let mut moves = Vec::new();
+        let mut total_time = None;
+        let mut unserviceable = Vec::new();
+        let mut late = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let result = if let Some(time) = line.strip_prefix("Total time:") {
+                time.trim()
+                    .parse::<u32>()
+                    .map(|time| total_time = Some(time))
+                    .map_err(|e| anyhow!("Invalid total time `{}`: {e}", time.trim()))
+            } else if let Some(orders) = line.strip_prefix("Unserviceable orders:") {
+                unserviceable = unescape_list(orders.trim());
+                Ok(())
+            } else if let Some(orders) = line.strip_prefix("Late orders:") {
+                late = unescape_list(orders.trim());
+                Ok(())
+            } else {
+                parse_move(line).map(|m| moves.push(m))
+            };
+
+            result.with_context(|| format!("Parse solution, line {}", index + 1))?;
+        }
+
+        let total_time =
+            total_time.ok_or_else(|| anyhow!("Parse solution: expected a `Total time:` line"))?;
+
+        Ok(Self {
+            moves,
+            total_time,
+            unserviceable,
+            late,
+        })
+    }
+}
+
+/// Parses a move `W=.., T=.., N1=.., P1=[..], N2=.., P2=[..]`.
+fn parse_move(line: &str) -> anyhow::Result<Move> {
+    fn split<'a>(text: &'a str, separator: &str) -> anyhow::Result<(&'a str, &'a str)> {
+        split_unescaped(text, separator)
+            .ok_or_else(|| anyhow!("Expected `{separator}` in `{text}`"))
+    }
+
+    let text = line
+        .strip_prefix("W=")
+        .ok_or_else(|| anyhow!("Expected a move starting with `W=`"))?;
+
+    let (time, text) = split(text, ", T=")?;
+    let (train, text) = split(text, ", N1=")?;
+    let (from, text) = split(text, ", P1=[")?;
+    let (load, text) = split(text, "], N2=")?;
+    let (to, text) = split(text, ", P2=[")?;
+    let unload = text
+        .strip_suffix(']')
+        .ok_or_else(|| anyhow!("Expected `]` at the end of the move"))?;
+
+    let time = time
+        .parse::<u32>()
+        .map_err(|e| anyhow!("Invalid time `{time}`: {e}"))?;
+
+    Ok(Move::new(
+        time,
+        unescape(train),
+        unescape(from),
+        unescape_list(load),
+        unescape(to),
+        unescape_list(unload),
+    ))
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if RESERVED.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_list(names: &[String]) -> String {
+    names.iter().map(|name| escape(name)).join(",")
+}
+
+fn unescape(text: &str) -> String {
+    let mut name = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => name.extend(chars.next()),
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// Names separated by commas that are not escaped, none in an empty list.
+fn unescape_list(mut list: &str) -> Vec<String> {
+    let mut names = vec![];
+    if list.is_empty() {
+        return names;
+    }
+    while let Some((name, rest)) = split_unescaped(list, ",") {
+        names.push(unescape(name));
+        list = rest;
+    }
+    names.push(unescape(list));
+    names
+}
+
+/// Splits `text` around the first `separator` that does not start with an escaped character.
+fn split_unescaped<'a>(text: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if text[index..].starts_with(separator) {
+            return Some((&text[..index], &text[index + separator.len()..]));
         }
     }
+    None
 }
 
-impl ToString for Solution {
-    fn to_string(&self) -> String {
-        let mut result = self
-            .moves
-            .iter()
-            .map(|m| {
-                format!(
-                    "W={}, T={}, N1={}, P1=[{}], N2={}, P2=[{}]",
-                    m.time,
-                    m.train,
-                    m.from,
-                    m.load.join(","),
-                    m.to,
-                    m.unload.join(","),
-                )
-            })
-            .collect::<Vec<String>>()
-            .join("\n");
-        result.push('\n');
-        result
+#[cfg(test)]
+mod tests {
+    use crate::{solve, Input, Solution};
+
+    #[test]
+    fn test_parse_round_trip() {
+        for data in [
+            include_str!("data/simple.txt"),
+            include_str!("data/generated.small.1.txt"),
+            include_str!("data/generated.small.multiload.txt"),
+            include_str!("data/generated.large.1.txt"),
+            include_str!("data/disconnected.txt"),
+        ] {
+            let input = Input::try_from(data).expect("Test input");
+            let solution = solve(&input).expect("Solve input").sort_by_time();
+
+            let sut = solution
+                .to_string()
+                .parse::<Solution>()
+                .expect("Parse solution");
+            assert_eq!(sut, solution);
+        }
+
+        // K1 can't make it by 20.
+        let input = Input::try_from(
+            "3\nA\nB\nC\n\n2\nE1,A,B,30\nE2,B,C,10\n\n1\nK1,5,A,C,,20\n\n1\nQ1,6,B\n",
+        )
+        .expect("Test input");
+        let solution = solve(&input).expect("Solve input");
+        assert_eq!(solution.late(), ["K1"]);
+        assert_eq!(
+            solution
+                .to_string()
+                .parse::<Solution>()
+                .expect("Parse solution"),
+            solution
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trip_reserved_characters() {
+        let input = Input::from_json(
+            r#"{
+                "stations": ["A]", "B=1"],
+                "edges": [{"name": "E1", "from": "A]", "to": "B=1", "distance": 30}],
+                "orders": [{"name": "K,1", "weight": 5, "location": "A]", "destination": "B=1"}],
+                "trains": [{"name": "Q[1]\\", "capacity": 10, "location": "B=1"}]
+            }"#,
+        )
+        .expect("Test input");
+        let solution = solve(&input).expect("Solve input").sort_by_time();
+
+        let text = solution.to_string();
+        assert!(text.contains(r"T=Q\[1\]\\, N1=A\], P1=[K\,1], N2=B\=1"));
+
+        let sut = text.parse::<Solution>().expect("Parse solution");
+        assert_eq!(sut, solution);
+
+        let unserviceable =
+            Solution::new(vec![], 0).with_unserviceable(vec!["K,1".into(), "K2".into()]);
+        assert_eq!(
+            unserviceable
+                .to_string()
+                .parse::<Solution>()
+                .expect("Parse solution"),
+            unserviceable
+        );
+    }
+
+    #[test]
+    fn test_parse_total_time() {
+        let sut = indoc::indoc! {"
+            W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]
+            W=30, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+
+            Total time: 75
+        "}
+        .parse::<Solution>()
+        .expect("Parse solution");
+
+        assert_eq!(sut.moves().len(), 2);
+        assert_eq!(sut.moves()[1].load(), ["K1"]);
+        assert_eq!(sut.total_time(), 75);
+    }
+
+    #[test]
+    fn test_parse_unserviceable_orders() {
+        let sut = indoc::indoc! {"
+            W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]
+            W=10, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+            W=25, T=Q1, N1=C, P1=[K4], N2=B, P2=[K4]
+
+            Total time: 30
+            Unserviceable orders: K2,K3
+        "}
+        .parse::<Solution>()
+        .expect("Parse solution");
+
+        assert_eq!(sut.moves().len(), 3);
+        assert_eq!(sut.unserviceable(), ["K2", "K3"]);
+    }
+
+    #[test]
+    fn test_parse_late_orders() {
+        let sut = indoc::indoc! {"
+            W=10, T=Q2, N1=D, P1=[K3], N2=B, P2=[K3]
+
+            Total time: 30
+            Late orders: K3
+        "}
+        .parse::<Solution>()
+        .expect("Parse solution");
+
+        assert_eq!(sut.late(), ["K3"]);
+        assert!(sut.unserviceable().is_empty());
+    }
+
+    #[test]
+    fn test_parse_missing_total_time() {
+        let error = "W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]"
+            .parse::<Solution>()
+            .expect_err("Invalid solution");
+
+        assert_eq!(
+            format!("{error:#}"),
+            "Parse solution: expected a `Total time:` line"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_move() {
+        let error = indoc::indoc! {"
+            W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]
+            W=30, T=Q1, N1=A, P1=[K1], P2=[K1]
+        "}
+        .parse::<Solution>()
+        .expect_err("Invalid solution");
+
+        assert_eq!(
+            format!("{error:#}"),
+            "Parse solution, line 2: Expected `], N2=` in `K1], P2=[K1]`"
+        );
     }
 }
@@ -0,0 +1,360 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use anyhow::bail;
+use itertools::Itertools;
+
+use crate::model::{Edge, Station, Train};
+use crate::network::Network;
+use crate::output::Move;
+use crate::solver::UNREACHABLE;
+use crate::timeline::Timeline;
+use crate::{Input, Solution};
+
+/// Time a train occupies an edge, from the start inclusive to the end exclusive.
+pub(crate) type Interval = (u32, u32);
+
+/// Edges a train travels through during move `m`, with intervals relative to the departure.
+pub(crate) fn legs<'n>(
+    network: &Network<'n>,
+    train: &Train,
+    m: &Move,
+) -> Option<Vec<(&'n Edge, Interval)>> {
+    let path = network.path(&m.from().into(), &m.to().into())?;
+
+    let mut distance = 0;
+    Some(
+        path.into_iter()
+            .map(|(_, _, edge)| {
+                let start = train.travel_time(distance);
+                distance += edge.distance();
+                (edge, (start, train.travel_time(distance)))
+            })
+            .collect(),
+    )
+}
+
+/// The most trains on an edge at once during `interval`, given `reserved` intervals of the edge.
+fn peak_usage(reserved: &[Interval], (start, end): Interval) -> u32 {
+    let mut usage = 0;
+    let mut peak = 0;
+
+    // Trains leaving the edge make room before others enter it at the same time.
+    for (_, change) in reserved
+        .iter()
+        .filter(|(s, e)| *s < end && start < *e)
+        .flat_map(|(s, e)| [(*s.max(&start), 1), (*e, -1)])
+        .sorted()
+    {
+        usage += change;
+        peak = peak.max(usage);
+    }
+
+    peak as u32
+}
+
+/// Delays departures of `solution` moves so that no edge is used by more trains at once than
+/// its capacity, and no station holds more trains than its capacity.
+/// A train waits at the departure station until the whole path of a move is clear and there is
+/// room at the arrival station, so moves keep their stations and loads, and only their times change.
+/// Waiting between moves of a train in the original solution is kept.
+///
+/// Moves are scheduled in time order. A train takes a place at the arrival station when it departs,
+/// and frees its place at the departure station, so a full station is never entered.
+/// It fails if trains wait for room at stations occupied by each other.
+pub(crate) fn schedule(
+    input: &Input,
+    network: &Network,
+    solution: Solution,
+) -> anyhow::Result<Solution> {
+    if solution.moves().is_empty()
+        || input.edges().iter().all(|e| e.capacity().is_none())
+            && input
+                .stations()
+                .iter()
+                .all(|s| input.station_capacity(s).is_none())
+    {
+        return Ok(solution);
+    }
+
+    let mut moves = Vec::with_capacity(solution.moves().len());
+
+    let mut schedules = HashMap::new();
+    for (name, train_moves) in solution
+        .moves()
+        .iter()
+        .sorted_by_key(|m| m.time())
+        .into_group_map_by(|m| m.train())
+    {
+        match input.trains().iter().find(|t| t.name() == name) {
+            Some(train) => {
+                schedules.insert(
+                    name,
+                    TrainSchedule {
+                        train,
+                        moves: train_moves.into(),
+                        planned_arrival: 0,
+                        arrival: 0,
+                    },
+                );
+            }
+            // Moves of unknown trains are left for the verifier to report.
+            None => moves.extend(train_moves.into_iter().cloned()),
+        }
+    }
+
+    let mut occupancy: HashMap<Station, u32> = HashMap::new();
+    for train in input.trains() {
+        *occupancy.entry(train.location().clone()).or_default() += 1;
+    }
+
+    let mut queue: BinaryHeap<Reverse<(u32, &str)>> = schedules
+        .iter()
+        .filter_map(|(name, schedule)| Some(Reverse((schedule.ready()?, *name))))
+        .collect();
+
+    let mut reserved: HashMap<&str, Vec<Interval>> = HashMap::new();
+    // Trains that wait for room at a station.
+    let mut blocked: HashMap<Station, Vec<&str>> = HashMap::new();
+
+    while let Some(Reverse((departure, name))) = queue.pop() {
+        let schedule = schedules.get_mut(name).expect("Scheduled train");
+        let m = *schedule.moves.front().expect("Next move");
+
+        let legs = legs(network, schedule.train, m).unwrap_or_default();
+
+        if let Some(delay) = delay(&reserved, &legs, departure) {
+            queue.push(Reverse((departure + delay, name)));
+            continue;
+        }
+
+        let (from, to) = (Station::from(m.from()), Station::from(m.to()));
+
+        if from != to {
+            if let Some(capacity) = input.station_capacity(&to) {
+                if occupancy.get(&to).copied().unwrap_or_default() >= capacity {
+                    blocked.entry(to).or_default().push(name);
+                    continue;
+                }
+            }
+        }
+
+        for (edge, (start, end)) in &legs {
+            if edge.capacity().is_some() {
+                reserved
+                    .entry(edge.name())
+                    .or_default()
+                    .push((departure + start, departure + end));
+            }
+        }
+
+        let from_occupancy = occupancy.entry(from.clone()).or_default();
+        *from_occupancy = from_occupancy.saturating_sub(1);
+        *occupancy.entry(to).or_default() += 1;
+
+        // The place the train left may be awaited by others.
+        for waiting in blocked.remove(&from).unwrap_or_default() {
+            queue.push(Reverse((departure, waiting)));
+        }
+
+        let travel_time = legs.last().map(|(_, (_, end))| *end).unwrap_or_default();
+        schedule.planned_arrival = m.time() + travel_time;
+        schedule.arrival = departure + travel_time;
+        schedule.moves.pop_front();
+
+        moves.push(m.departing_at(departure));
+
+        if let Some(ready) = schedule.ready() {
+            queue.push(Reverse((ready, name)));
+        }
+    }
+
+    if !blocked.is_empty() {
+        bail!(
+            "Trains are deadlocked waiting for room at stations: {}",
+            blocked
+                .iter()
+                .sorted_by_key(|(station, _)| *station)
+                .map(|(station, trains)| format!(
+                    "{} at {station}",
+                    trains.iter().sorted().join(",")
+                ))
+                .join(", ")
+        );
+    }
+
+    let distance = |from: &Station, to: &Station| network.distance(from, to).unwrap_or(UNREACHABLE);
+    let timeline = Timeline::new(input.orders(), input.trains(), &distance, &moves);
+
+    let late = input
+        .orders()
+        .iter()
+        .filter(|o| matches!(timeline.delivered_at(o.name()), Some(time) if o.is_late(time)))
+        .map(|o| o.name().to_owned())
+        .collect();
+
+    let total_time = timeline.makespan();
+
+    Ok(Solution::new(moves, total_time)
+        .with_unserviceable(solution.unserviceable().to_vec())
+        .with_late(late))
+}
+
+/// Moves of a train left to schedule.
+struct TrainSchedule<'s> {
+    train: &'s Train,
+    moves: VecDeque<&'s Move>,
+    /// Arrival of the last scheduled move in the original solution and in the schedule.
+    planned_arrival: u32,
+    arrival: u32,
+}
+
+impl TrainSchedule<'_> {
+    /// The earliest departure of the next move, keeping the original wait before it.
+    fn ready(&self) -> Option<u32> {
+        let m = self.moves.front()?;
+        let dwell = m.time().saturating_sub(self.planned_arrival);
+        Some(m.time().max(self.arrival + dwell))
+    }
+}
+
+/// How long to delay `departure` until the first congested edge on the way has room,
+/// `None` if every edge has room.
+fn delay(
+    reserved: &HashMap<&str, Vec<Interval>>,
+    legs: &[(&Edge, Interval)],
+    departure: u32,
+) -> Option<u32> {
+    legs.iter().find_map(|(edge, (start, end))| {
+        let capacity = edge.capacity()?;
+        let reserved = reserved.get(edge.name())?;
+        let interval = (departure + start, departure + end);
+
+        if interval.0 == interval.1 || peak_usage(reserved, interval) < capacity {
+            return None;
+        }
+
+        // The earliest time another train leaves the edge.
+        reserved
+            .iter()
+            .filter(|(s, e)| *s < interval.1 && interval.0 < *e)
+            .map(|(_, e)| e - interval.0)
+            .min()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::{DistanceBackend, Network};
+    use crate::output::Move;
+    use crate::scheduler::schedule;
+    use crate::{verify, Input, Solution};
+
+    #[test]
+    fn test_schedule_single_track() {
+        let input = Input::try_from(include_str!("data/single_track.txt")).expect("Test input");
+        let network = Network::new(&input, DistanceBackend::Dijkstra);
+
+        // Both trains enter the single-track edge E2 from opposite ends at once.
+        let solution = Solution::new(
+            vec![
+                Move::new(
+                    0,
+                    "Q1".to_owned(),
+                    "A".to_owned(),
+                    vec!["K1".to_owned()],
+                    "C".to_owned(),
+                    vec!["K1".to_owned()],
+                ),
+                Move::new(
+                    0,
+                    "Q2".to_owned(),
+                    "C".to_owned(),
+                    vec!["K2".to_owned()],
+                    "A".to_owned(),
+                    vec!["K2".to_owned()],
+                ),
+            ],
+            30,
+        );
+        let sut = schedule(&input, &network, solution).expect("Schedule moves");
+
+        assert_eq!(
+            &sut.to_string(),
+            indoc::indoc! {"
+            W=0, T=Q1, N1=A, P1=[K1], N2=C, P2=[K1]
+            W=30, T=Q2, N1=C, P1=[K2], N2=A, P2=[K2]
+
+            Total time: 60
+        "}
+        );
+        assert_eq!(sut.total_time(), 60);
+        verify(&input, &sut).expect("Valid solution");
+    }
+
+    fn mv(time: u32, train: &str, from: &str, order: &str, to: &str) -> Move {
+        let load = vec![order.to_owned()];
+        Move::new(
+            time,
+            train.to_owned(),
+            from.to_owned(),
+            load.clone(),
+            to.to_owned(),
+            load,
+        )
+    }
+
+    #[test]
+    fn test_hold_train_until_station_has_room() {
+        let input = Input::try_from(include_str!("data/station_capacity.txt")).expect("Test input");
+        let network = Network::new(&input, DistanceBackend::Dijkstra);
+
+        // Both trains stop at B that has room for one train.
+        let solution = Solution::new(
+            vec![
+                mv(0, "Q1", "A", "K1", "B"),
+                mv(10, "Q1", "B", "K1", "C"),
+                mv(0, "Q2", "C", "K2", "B"),
+                mv(10, "Q2", "B", "K2", "A"),
+            ],
+            20,
+        );
+        let sut = schedule(&input, &network, solution).expect("Schedule moves");
+
+        assert_eq!(
+            &sut.to_string(),
+            indoc::indoc! {"
+            W=0, T=Q1, N1=A, P1=[K1], N2=B, P2=[K1]
+            W=10, T=Q1, N1=B, P1=[K1], N2=C, P2=[K1]
+            W=10, T=Q2, N1=C, P1=[K2], N2=B, P2=[K2]
+            W=20, T=Q2, N1=B, P1=[K2], N2=A, P2=[K2]
+
+            Total time: 30
+        "}
+        );
+        assert_eq!(sut.total_time(), 30);
+        verify(&input, &sut).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_detect_deadlock() {
+        let input = Input::try_from(
+            "2\nA,1\nB,1\n\n1\nE1,A,B,10\n\n2\nK1,1,A,B\nK2,1,B,A\n\n2\nQ1,1,A\nQ2,1,B\n",
+        )
+        .expect("Test input");
+        let network = Network::new(&input, DistanceBackend::Dijkstra);
+
+        let solution = Solution::new(
+            vec![mv(0, "Q1", "A", "K1", "B"), mv(0, "Q2", "B", "K2", "A")],
+            10,
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                schedule(&input, &network, solution).expect_err("Deadlock")
+            ),
+            "Trains are deadlocked waiting for room at stations: Q2 at A, Q1 at B"
+        );
+    }
+}
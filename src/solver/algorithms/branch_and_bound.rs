@@ -0,0 +1,609 @@
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use itertools::Itertools;
+
+use crate::model::{Order, Station, Train};
+use crate::output::Move;
+use crate::solver::utils::UNREACHABLE;
+use crate::solver::{Algorithm, NearestTrainSingleOrderAlgorithm};
+use crate::Solution;
+
+/// Limits that stop the exact search on instances too big to solve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// The most partial plans to explore.
+    pub nodes: usize,
+    /// The longest time to search.
+    pub time: Duration,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            nodes: 1_000_000,
+            time: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Result of the exact search.
+#[derive(Debug)]
+pub struct SearchOutcome {
+    pub solution: Solution,
+    /// Whether the search finished, so no plan has fewer late orders or a shorter total time.
+    pub optimal: bool,
+    /// Number of partial plans explored.
+    pub nodes: usize,
+}
+
+/// Branch-and-bound over plans where every train visits a sequence of stations,
+/// picking up orders anywhere on the way and carrying as many as its capacity allows.
+/// Plans are ranked by the number of late orders, and then by the total time.
+///
+/// A train unloads orders as soon as it reaches their destination, and it waits at a station
+/// until all orders it loads there are ready. The search starts from the `NearestTrainSingleOrderAlgorithm`
+/// solution, and returns the best plan found when it runs out of limits.
+#[derive(Debug, Default)]
+pub struct BranchAndBoundAlgorithm {
+    limits: SearchLimits,
+}
+
+impl BranchAndBoundAlgorithm {
+    pub fn new(limits: SearchLimits) -> Self {
+        Self { limits }
+    }
+
+    /// Searches for the best plan, and tells whether it is proven optimal.
+    pub fn search(
+        &self,
+        orders: &[Order],
+        trains: &[Train],
+        distance: &dyn Fn(&Station, &Station) -> u32,
+    ) -> anyhow::Result<SearchOutcome> {
+        let orders = orders
+            .iter()
+            .filter(|o| !o.is_delivered())
+            .cloned()
+            .collect_vec();
+
+        let mut search = Search {
+            orders: &orders,
+            trains,
+            distance,
+            limits: self.limits,
+            started: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            best: None,
+        };
+
+        // The greedy solution bounds the search from the start, if there is one.
+        if let Ok(solution) =
+            NearestTrainSingleOrderAlgorithm.solve(orders.clone(), trains.to_vec(), distance)
+        {
+            search.best = Some((
+                (solution.late().len(), solution.total_time()),
+                Plan::Greedy(solution),
+            ));
+        }
+
+        search.branch(&State::new(&orders, trains), (0, 0));
+
+        let optimal = !search.stopped;
+        let nodes = search.nodes;
+
+        let solution = match search.best.take() {
+            Some((_, Plan::Greedy(solution))) => solution,
+            Some((_, Plan::Decisions(decisions))) => search.replay(&decisions),
+            None if optimal => bail!("There is no plan that delivers all orders"),
+            None => bail!("No plan found after exploring {nodes} partial plans"),
+        };
+
+        Ok(SearchOutcome {
+            solution,
+            optimal,
+            nodes,
+        })
+    }
+}
+
+impl Algorithm for BranchAndBoundAlgorithm {
+    fn solve(
+        &self,
+        orders: Vec<Order>,
+        trains: Vec<Train>,
+        distance: &dyn Fn(&Station, &Station) -> u32,
+    ) -> anyhow::Result<Solution> {
+        self.search(&orders, &trains, distance)
+            .map(|outcome| outcome.solution)
+    }
+
+    fn sort_sensitive(&self) -> bool {
+        false
+    }
+}
+
+/// The next thing a train does.
+#[derive(Debug, Clone, Copy)]
+enum Decision {
+    /// Go to the order location, if the train is not there, and load the order.
+    Pickup { train: usize, order: usize },
+    /// Go to the destination of an order on board and unload it with others delivered there.
+    Deliver { train: usize, order: usize },
+}
+
+impl Decision {
+    fn train(&self) -> usize {
+        match *self {
+            Decision::Pickup { train, .. } | Decision::Deliver { train, .. } => train,
+        }
+    }
+}
+
+enum Plan {
+    Greedy(Solution),
+    Decisions(Vec<Decision>),
+}
+
+/// Number of late orders and the total time.
+type Cost = (usize, u32);
+
+/// A train during the search, at the station it has arrived to last.
+#[derive(Debug, Clone)]
+struct TrainState {
+    location: Station,
+    arrival: u32,
+    /// Handling time of orders unloaded and loaded at the location.
+    unloading: u32,
+    loading: u32,
+    /// When all orders loaded at the location are ready.
+    ready_to_load: u32,
+    on_board: Vec<usize>,
+    weight: u32,
+    /// The lowest order index the train may still load at the location without moving.
+    /// Orders loaded at one stop are picked up in the index order, and a train that arrives
+    /// to deliver loads nothing more, as picking up on the way covers it.
+    next_pickup: usize,
+}
+
+impl TrainState {
+    /// When the train can depart, orders are unloaded first and then loaded.
+    fn departure(&self) -> u32 {
+        (self.arrival + self.unloading).max(self.ready_to_load) + self.loading
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    trains: Vec<TrainState>,
+    picked_up: Vec<bool>,
+    delivered: usize,
+    late: Vec<usize>,
+    decisions: Vec<Decision>,
+}
+
+impl State {
+    fn new(orders: &[Order], trains: &[Train]) -> Self {
+        Self {
+            trains: trains
+                .iter()
+                .map(|t| TrainState {
+                    location: t.location().clone(),
+                    arrival: t.traveled_time(),
+                    unloading: 0,
+                    loading: 0,
+                    ready_to_load: 0,
+                    on_board: vec![],
+                    weight: 0,
+                    next_pickup: 0,
+                })
+                .collect(),
+            picked_up: vec![false; orders.len()],
+            delivered: 0,
+            late: vec![],
+            decisions: vec![],
+        }
+    }
+}
+
+struct Search<'s> {
+    orders: &'s [Order],
+    trains: &'s [Train],
+    distance: &'s dyn Fn(&Station, &Station) -> u32,
+    limits: SearchLimits,
+    started: Instant,
+    nodes: usize,
+    stopped: bool,
+    best: Option<(Cost, Plan)>,
+}
+
+impl Search<'_> {
+    /// Explores plans that extend `state`. Decisions are made in the order of departures,
+    /// the last one departing at `last` by the train with the index, so every plan is explored once.
+    fn branch(&mut self, state: &State, last: (u32, usize)) {
+        if self.stopped {
+            return;
+        }
+
+        self.nodes += 1;
+        if self.nodes >= self.limits.nodes || self.started.elapsed() >= self.limits.time {
+            self.stopped = true;
+            return;
+        }
+
+        if state.delivered == self.orders.len() {
+            if let Some(cost) = self.finish(state) {
+                if !matches!(&self.best, Some((best, _)) if *best <= cost) {
+                    self.best = Some((cost, Plan::Decisions(state.decisions.clone())));
+                }
+            }
+            return;
+        }
+
+        let Some(bound) = self.bound(state, last) else {
+            return;
+        };
+        if matches!(&self.best, Some((best, _)) if bound >= *best) {
+            return;
+        }
+
+        let mut children = self
+            .decisions(state, last)
+            .into_iter()
+            .map(|decision| {
+                let mut child = state.clone();
+                let departure = child.trains[decision.train()].departure();
+                self.apply(&mut child, decision, None);
+                child.decisions.push(decision);
+                (child, (departure, decision.train()))
+            })
+            .collect_vec();
+
+        // Trains that are free earlier go first, so good plans are found early.
+        children.sort_by_key(|(child, (_, train))| child.trains[*train].departure());
+
+        for (child, key) in children {
+            self.branch(&child, key);
+        }
+    }
+
+    /// Decisions a train can make without departing before the last decision.
+    fn decisions(&self, state: &State, last: (u32, usize)) -> Vec<Decision> {
+        let mut decisions = vec![];
+
+        for (index, train) in state.trains.iter().enumerate() {
+            if (train.departure(), index) < last {
+                continue;
+            }
+
+            for &order in train
+                .on_board
+                .iter()
+                .unique_by(|&&o| self.orders[o].destination())
+            {
+                decisions.push(Decision::Deliver {
+                    train: index,
+                    order,
+                });
+            }
+
+            for (order, details) in self.orders.iter().enumerate() {
+                let station = details.location();
+                // Orders destined to another station are unloaded there before loading.
+                let weight = if train.location == station {
+                    if order < train.next_pickup {
+                        continue;
+                    }
+                    train.weight
+                } else {
+                    train.weight
+                        - train
+                            .on_board
+                            .iter()
+                            .filter(|&&o| self.orders[o].destination() == station)
+                            .map(|&o| self.orders[o].weight())
+                            .sum::<u32>()
+                };
+
+                if !state.picked_up[order]
+                    && weight + details.weight() <= self.trains[index].capacity()
+                    && (self.distance)(&train.location, &details.location()) != UNREACHABLE
+                    && (self.distance)(&details.location(), &details.destination()) != UNREACHABLE
+                {
+                    decisions.push(Decision::Pickup {
+                        train: index,
+                        order,
+                    });
+                }
+            }
+        }
+
+        decisions
+    }
+
+    /// Applies `decision` to `state`, and records the move if the train departs.
+    fn apply(&self, state: &mut State, decision: Decision, moves: Option<&mut Vec<Move>>) {
+        let (index, station) = match decision {
+            Decision::Pickup { train, order } => (train, self.orders[order].location()),
+            Decision::Deliver { train, order } => (train, self.orders[order].destination()),
+        };
+
+        let train = &mut state.trains[index];
+
+        if train.location != station {
+            let departure = train.departure();
+
+            if let Some(moves) = moves {
+                let names = train
+                    .on_board
+                    .iter()
+                    .map(|&o| self.orders[o].name().to_owned())
+                    .collect_vec();
+                moves.push(Move::new(
+                    departure,
+                    self.trains[index].name().to_owned(),
+                    train.location.name().to_owned(),
+                    names.clone(),
+                    station.name().to_owned(),
+                    names,
+                ));
+            }
+
+            let distance = (self.distance)(&train.location, &station);
+            train.arrival = departure + self.trains[index].travel_time(distance);
+            train.location = station;
+            train.loading = 0;
+            train.ready_to_load = 0;
+            train.next_pickup = usize::MAX;
+
+            // Orders destined to the station leave the train together.
+            let (unloaded, on_board): (Vec<_>, Vec<_>) = train
+                .on_board
+                .iter()
+                .partition(|&&o| self.orders[o].destination() == train.location);
+
+            train.on_board = on_board;
+            train.unloading = unloaded
+                .iter()
+                .map(|&o| self.orders[o].handling_time())
+                .sum();
+
+            let delivered_at = train.arrival + train.unloading;
+            for order in unloaded {
+                train.weight -= self.orders[order].weight();
+                state.delivered += 1;
+                if self.orders[order].is_late(delivered_at) {
+                    state.late.push(order);
+                }
+            }
+        }
+
+        if let Decision::Pickup { order, .. } = decision {
+            let details = &self.orders[order];
+            train.loading += details.handling_time();
+            train.ready_to_load = train.ready_to_load.max(details.earliest_pickup());
+            train.weight += details.weight();
+            train.on_board.push(order);
+            train.next_pickup = order + 1;
+            state.picked_up[order] = true;
+        }
+    }
+
+    /// The cost of a complete plan after trains return to their end stations,
+    /// `None` if some train can't return.
+    fn finish(&self, state: &State) -> Option<Cost> {
+        let mut makespan = 0;
+
+        for (train, details) in state.trains.iter().zip(self.trains) {
+            makespan = makespan.max(
+                train
+                    .departure()
+                    .checked_add(self.return_time(details, &train.location)?)?,
+            );
+        }
+
+        Some((state.late.len(), makespan))
+    }
+
+    /// Time for a train to return from `location` to its end station, `None` if it can't.
+    fn return_time(&self, train: &Train, location: &Station) -> Option<u32> {
+        match train.end_station() {
+            Some(end_station) => match (self.distance)(location, end_station) {
+                UNREACHABLE => None,
+                distance => Some(train.travel_time(distance)),
+            },
+            None => Some(0),
+        }
+    }
+
+    /// A cost no plan extending `state` can beat, `None` if no plan can complete it.
+    /// Every order on board has to be delivered by its train, and every other order
+    /// has to be picked up and delivered by some train that can still make decisions,
+    /// which then has to return to its end station.
+    fn bound(&self, state: &State, last: (u32, usize)) -> Option<Cost> {
+        let mut late = state.late.len();
+        let mut makespan = 0;
+
+        let available = |index: usize| (state.trains[index].departure(), index) >= last;
+
+        for (index, (train, details)) in state.trains.iter().zip(self.trains).enumerate() {
+            if !available(index) {
+                if !train.on_board.is_empty() {
+                    return None;
+                }
+                makespan =
+                    makespan.max(train.departure() + self.return_time(details, &train.location)?);
+                continue;
+            }
+
+            makespan =
+                makespan.max(train.departure() + self.return_time(details, &train.location)?);
+
+            for &order in &train.on_board {
+                let order = &self.orders[order];
+                let delivered_at = train.departure()
+                    + details.travel_time((self.distance)(&train.location, &order.destination()))
+                    + order.handling_time();
+
+                if order.is_late(delivered_at) {
+                    late += 1;
+                }
+                makespan =
+                    makespan.max(delivered_at + self.return_time(details, &order.destination())?);
+            }
+        }
+
+        for (order, details) in self.orders.iter().enumerate() {
+            if state.picked_up[order] {
+                continue;
+            }
+
+            let (delivered_at, finished_at) = state
+                .trains
+                .iter()
+                .zip(self.trains)
+                .enumerate()
+                .filter(|(index, (_, train))| {
+                    available(*index) && train.capacity() >= details.weight()
+                })
+                .filter_map(|(_, (state, train))| {
+                    let to_pickup = (self.distance)(&state.location, &details.location());
+                    let to_destination =
+                        (self.distance)(&details.location(), &details.destination());
+                    if to_pickup == UNREACHABLE || to_destination == UNREACHABLE {
+                        return None;
+                    }
+
+                    let picked_up_at = (state.departure() + train.travel_time(to_pickup))
+                        .max(details.earliest_pickup())
+                        + details.handling_time();
+                    let delivered_at =
+                        picked_up_at + train.travel_time(to_destination) + details.handling_time();
+
+                    Some((
+                        delivered_at,
+                        delivered_at + self.return_time(train, &details.destination())?,
+                    ))
+                })
+                .fold(
+                    None,
+                    |earliest: Option<(u32, u32)>, (delivered, finished)| {
+                        Some(match earliest {
+                            Some((d, f)) => (d.min(delivered), f.min(finished)),
+                            None => (delivered, finished),
+                        })
+                    },
+                )?;
+
+            if details.is_late(delivered_at) {
+                late += 1;
+            }
+            makespan = makespan.max(finished_at);
+        }
+
+        Some((late, makespan))
+    }
+
+    /// Moves and the cost of the plan made of `decisions`.
+    fn replay(&self, decisions: &[Decision]) -> Solution {
+        let mut state = State::new(self.orders, self.trains);
+        let mut moves = vec![];
+
+        for &decision in decisions {
+            self.apply(&mut state, decision, Some(&mut moves));
+        }
+
+        let mut total_time = 0;
+
+        for (train, details) in state.trains.iter().zip(self.trains) {
+            let mut finished_at = train.departure();
+
+            if let Some(end_station) = details.end_station() {
+                if &train.location != end_station {
+                    moves.push(Move::new(
+                        finished_at,
+                        details.name().to_owned(),
+                        train.location.name().to_owned(),
+                        vec![],
+                        end_station.name().to_owned(),
+                        vec![],
+                    ));
+                    finished_at +=
+                        details.travel_time((self.distance)(&train.location, end_station));
+                }
+            }
+
+            total_time = total_time.max(finished_at);
+        }
+
+        let late = state
+            .late
+            .iter()
+            .sorted()
+            .map(|&o| self.orders[o].name().to_owned())
+            .collect();
+
+        Solution::new(moves, total_time).with_late(late)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::Network;
+    use crate::solver::algorithms::branch_and_bound::{BranchAndBoundAlgorithm, SearchLimits};
+    use crate::solver::{Algorithm, NearestTrainSingleOrderAlgorithm, UNREACHABLE};
+    use crate::{verify, Input};
+
+    #[test]
+    fn test_prove_optimal_plans() {
+        for (data, total_time) in [
+            (include_str!("../../data/simple.txt"), 70),
+            (include_str!("../../data/time_windows.txt"), 70),
+            (include_str!("../../data/service_times.txt"), 70),
+            (include_str!("../../data/home_depot.txt"), 40),
+            (include_str!("../../data/generated.small.1.txt"), 193),
+        ] {
+            let input = Input::try_from(data).expect("Test input");
+            let network = Network::from(&input);
+            let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+            let sut = BranchAndBoundAlgorithm::default()
+                .search(input.orders(), input.trains(), &distance)
+                .expect("Search");
+
+            assert!(sut.optimal, "Optimal after {} nodes", sut.nodes);
+            assert_eq!(sut.solution.total_time(), total_time);
+            verify(&input, &sut.solution).expect("Valid solution");
+
+            let greedy = NearestTrainSingleOrderAlgorithm
+                .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+                .expect("Greedy solution");
+            assert!(sut.solution.total_time() <= greedy.total_time());
+        }
+    }
+
+    #[test]
+    fn test_stop_at_node_limit() {
+        let input = Input::try_from(include_str!("../../data/generated.small.multiload.txt"))
+            .expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let sut = BranchAndBoundAlgorithm::new(SearchLimits {
+            nodes: 1000,
+            ..Default::default()
+        })
+        .search(input.orders(), input.trains(), &distance)
+        .expect("Search");
+
+        assert!(!sut.optimal);
+        assert_eq!(sut.nodes, 1000);
+        verify(&input, &sut.solution).expect("Valid solution");
+
+        let greedy = NearestTrainSingleOrderAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Greedy solution");
+        assert!(sut.solution.total_time() <= greedy.total_time());
+    }
+}
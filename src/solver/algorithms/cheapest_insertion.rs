@@ -0,0 +1,159 @@
+use anyhow::bail;
+use itertools::Itertools;
+
+use crate::model::{Order, Station, Train};
+use crate::solver::route::{Route, Router, Visit};
+use crate::solver::Algorithm;
+use crate::timeline::Timeline;
+use crate::Solution;
+
+// Goes through orders, inserting the pickup and the delivery of each one into the route of a train
+// where they cost the least: the fewest new late orders, and then the least extra time of the train.
+// A train carries any orders its capacity allows, and picks up and delivers them in any sequence,
+// so a move may carry orders loaded at different stations.
+#[derive(Debug)]
+pub struct CheapestInsertionAlgorithm;
+
+/// Late orders of a route, and the time its train finishes.
+type Cost = (usize, u32);
+
+impl Algorithm for CheapestInsertionAlgorithm {
+    fn solve(
+        &self,
+        orders: Vec<Order>,
+        trains: Vec<Train>,
+        distance: &dyn Fn(&Station, &Station) -> u32,
+    ) -> anyhow::Result<Solution> {
+        let orders = orders
+            .into_iter()
+            .filter(|order| !order.is_delivered())
+            .collect_vec();
+        let router = Router::new(&orders, &trains, distance);
+
+        let mut routes = vec![Route::new(); trains.len()];
+        // Trains that can't return to their end stations take no orders.
+        let mut costs = (0..trains.len())
+            .map(|train| cost(&router, train, &[]))
+            .collect_vec();
+
+        for (index, order) in orders.iter().enumerate() {
+            let makespan = costs.iter().flatten().map(|(_, time)| *time).max();
+            let cheapest = (0..trains.len())
+                .filter(|&train| trains[train].capacity() >= order.weight())
+                .filter_map(|train| Some((train, costs[train]?)))
+                .flat_map(|(train, (late, finished_at))| {
+                    let router = &router;
+                    insertions(&routes[train], index).filter_map(move |route| {
+                        let cost = cost(router, train, &route)?;
+                        let extra = (
+                            cost.0 as i64 - late as i64,
+                            makespan.unwrap_or_default().max(cost.1),
+                            cost.1 as i64 - finished_at as i64,
+                        );
+                        Some((extra, train, route, cost))
+                    })
+                })
+                .min_by_key(|(extra, ..)| *extra);
+
+            let Some((_, train, route, cost)) = cheapest else {
+                bail!(
+                    "There is no train that can deliver an order because it is too big or out of reach, order={}, weight={}",
+                    order.name(),
+                    order.weight()
+                );
+            };
+
+            log::debug!(
+                "ORDER {} inserted into the route of {}, late={}, finished_at={}",
+                order.name(),
+                trains[train].name(),
+                cost.0,
+                cost.1
+            );
+
+            routes[train] = route;
+            costs[train] = Some(cost);
+        }
+
+        let moves = router
+            .moves(&routes)
+            .expect("Routes are feasible once orders are inserted");
+
+        let timeline = Timeline::new(&orders, &trains, distance, &moves);
+        let late = orders
+            .iter()
+            .filter(|o| matches!(timeline.delivered_at(o.name()), Some(time) if o.is_late(time)))
+            .map(|o| o.name().to_owned())
+            .collect_vec();
+        let total_time = timeline.makespan();
+
+        Ok(Solution::new(moves, total_time).with_late(late))
+    }
+
+    fn sort_sensitive(&self) -> bool {
+        true
+    }
+}
+
+/// Routes with the pickup of `order` inserted at every place of `route`, and its delivery at every place after it.
+fn insertions(route: &Route, order: usize) -> impl Iterator<Item = Route> + '_ {
+    (0..=route.len())
+        .flat_map(move |pickup| (pickup..=route.len()).map(move |delivery| (pickup, delivery)))
+        .map(move |(pickup, delivery)| {
+            let mut route = route.clone();
+            route.insert(delivery, Visit::Deliver(order));
+            route.insert(pickup, Visit::Pickup(order));
+            route
+        })
+}
+
+/// Cost of `route` of `train`, `None` if it is not feasible.
+fn cost(router: &Router, train: usize, route: &[Visit]) -> Option<Cost> {
+    let schedule = router.simulate(train, route, None)?;
+
+    let late = schedule
+        .delivered_at
+        .iter()
+        .filter(|&&(order, time)| router.orders[order].is_late(time))
+        .count();
+    let finished_at = schedule
+        .finished_at
+        .unwrap_or(router.trains[train].traveled_time());
+
+    Some((late, finished_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::Network;
+    use crate::solver::{
+        Algorithm, CheapestInsertionAlgorithm, NearestTrainSingleOrderAlgorithm, UNREACHABLE,
+    };
+    use crate::{verify, Input};
+
+    #[test]
+    fn test_carry_mixed_loads() {
+        let input = Input::try_from(include_str!("../../data/generated.small.multiload.txt"))
+            .expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let sut = CheapestInsertionAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Solution");
+        verify(&input, &sut).expect("Valid solution");
+
+        // Some move carries an order picked up before, and one loaded at its departure station.
+        assert!(sut
+            .moves()
+            .windows(2)
+            .any(|pair| pair[0].train() == pair[1].train()
+                && pair[1].load().iter().any(|o| pair[0].unload().contains(o))
+                && pair[1].load().iter().any(|o| !pair[0].unload().contains(o))));
+
+        let single = NearestTrainSingleOrderAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Single-order solution");
+        assert!(sut.total_time() < single.total_time());
+    }
+}
@@ -1,15 +1,23 @@
+mod branch_and_bound;
+mod cheapest_insertion;
 mod nearest_train_order_collection;
 mod nearest_train_order_distribution;
 mod nearest_train_single_order;
+mod simulated_annealing;
 
+pub use branch_and_bound::{BranchAndBoundAlgorithm, SearchLimits, SearchOutcome};
+pub use cheapest_insertion::CheapestInsertionAlgorithm;
 pub use nearest_train_order_collection::NearestTrainOrderCollectionAlgorithm;
 pub use nearest_train_order_distribution::NearestTrainOrderDistributionAlgorithm;
 pub use nearest_train_single_order::NearestTrainSingleOrderAlgorithm;
+pub use simulated_annealing::{CoolingSchedule, SimulatedAnnealingAlgorithm};
+
+use clap::ValueEnum;
 
 use crate::model::{Order, Station, Train};
 use crate::Solution;
 
-pub trait Algorithm: std::fmt::Debug {
+pub trait Algorithm: std::fmt::Debug + Sync {
     fn solve(
         &self,
         orders: Vec<Order>,
@@ -19,3 +27,34 @@ pub trait Algorithm: std::fmt::Debug {
 
     fn sort_sensitive(&self) -> bool;
 }
+
+/// Construction algorithms that can be chosen to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AlgorithmKind {
+    /// Groups orders by destination, and sends the nearest train to collect them.
+    OrderCollection,
+    /// Groups orders by location, and sends the nearest train to pick them up and distribute.
+    OrderDistribution,
+    /// Sends the nearest train to deliver one order at a time.
+    SingleOrder,
+    /// Inserts every order into the route of a train where it costs the least, slow on many orders.
+    CheapestInsertion,
+}
+
+impl AlgorithmKind {
+    /// Algorithms that run unless others are chosen.
+    pub const DEFAULT: [AlgorithmKind; 3] = [
+        AlgorithmKind::OrderCollection,
+        AlgorithmKind::OrderDistribution,
+        AlgorithmKind::SingleOrder,
+    ];
+
+    pub fn algorithm(&self) -> &'static dyn Algorithm {
+        match self {
+            AlgorithmKind::OrderCollection => &NearestTrainOrderCollectionAlgorithm,
+            AlgorithmKind::OrderDistribution => &NearestTrainOrderDistributionAlgorithm,
+            AlgorithmKind::SingleOrder => &NearestTrainSingleOrderAlgorithm,
+            AlgorithmKind::CheapestInsertion => &CheapestInsertionAlgorithm,
+        }
+    }
+}
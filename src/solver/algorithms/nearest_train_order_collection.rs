@@ -5,6 +5,7 @@ use crate::model::{Order, Train};
 use crate::output::Move;
 use crate::solver::utils::{
     calculate_best_route_for_collection, find_nearest_train, group_orders_by_destination,
+    return_trains, UNREACHABLE,
 };
 use crate::solver::Algorithm;
 use crate::Solution;
@@ -25,6 +26,7 @@ impl Algorithm for NearestTrainOrderCollectionAlgorithm {
         let max_train_capacity = trains.iter().map(|t| t.capacity()).max().unwrap();
 
         let mut moves = Vec::new();
+        let mut late = Vec::new();
 
         orders.retain(|o| !o.is_delivered());
 
@@ -46,16 +48,32 @@ impl Algorithm for NearestTrainOrderCollectionAlgorithm {
                     while nearest_train.is_none() && !orders_to_pickup.is_empty() {
                         let total_weight = orders_to_pickup.iter().map(|o| o.weight()).sum::<u32>();
 
-                        let pickups = orders_to_pickup.iter().map(|o| o.location()).collect_vec();
+                        // Visit every pickup station once, even if it has few orders.
+                        let pickups = orders_to_pickup
+                            .iter()
+                            .map(|o| o.location())
+                            .unique()
+                            .collect_vec();
 
-                        route =
-                            calculate_best_route_for_collection(&distance, &pickups, destination).0;
+                        let length;
+                        (route, length) =
+                            calculate_best_route_for_collection(&distance, &pickups, destination);
 
-                        // Find the nearest train to the beginning of the route.
+                        // Find the nearest train to the beginning of the route,
+                        // unless some pickup can't be reached on the way.
                         let location = route.first().unwrap();
 
-                        nearest_train =
-                            find_nearest_train(&distance, &trains, location, total_weight);
+                        nearest_train = if length == UNREACHABLE {
+                            None
+                        } else {
+                            find_nearest_train(
+                                &distance,
+                                &trains,
+                                location,
+                                destination,
+                                total_weight,
+                            )
+                        };
 
                         if nearest_train.is_none() {
                             orders_to_pickup.pop();
@@ -104,6 +122,16 @@ impl Algorithm for NearestTrainOrderCollectionAlgorithm {
                                 .copied()
                                 .collect_vec();
 
+                            // Wait until orders at the station are ready for pickup, and load them.
+                            train.wait_until(
+                                to_collect
+                                    .iter()
+                                    .map(|o| o.earliest_pickup())
+                                    .max()
+                                    .unwrap_or_default(),
+                            );
+                            train.handle(to_collect.iter().map(|o| o.handling_time()).sum());
+
                             delivery.append(&mut to_collect);
 
                             let names = delivery.iter().map(|o| o.name().to_owned()).collect_vec();
@@ -136,6 +164,15 @@ impl Algorithm for NearestTrainOrderCollectionAlgorithm {
 
                         assert!(delivery.len() == orders_to_pickup.len());
 
+                        train.handle(delivery.iter().map(|o| o.handling_time()).sum());
+
+                        late.extend(
+                            delivery
+                                .iter()
+                                .filter(|o| o.is_late(train.traveled_time()))
+                                .map(|o| o.name().to_owned()),
+                        );
+
                         // Return train to the idle pool with updated time.
                         trains.push(train);
                     } else {
@@ -181,10 +218,13 @@ impl Algorithm for NearestTrainOrderCollectionAlgorithm {
                 );
             }
         }
+        return_trains(&distance, &mut trains, &mut moves)?;
+
         Ok(Solution::new(
             moves,
             trains.iter().map(|t| t.traveled_time()).max().unwrap(),
-        ))
+        )
+        .with_late(late))
     }
 
     fn sort_sensitive(&self) -> bool {
@@ -5,6 +5,7 @@ use crate::model::{Order, Train};
 use crate::output::Move;
 use crate::solver::utils::{
     calculate_best_route_for_distribution, find_nearest_train, group_orders_by_location,
+    return_trains, UNREACHABLE,
 };
 use crate::Solution;
 
@@ -24,6 +25,7 @@ impl Algorithm for NearestTrainOrderDistributionAlgorithm {
         let mut trains = trains;
 
         let mut moves = Vec::new();
+        let mut late = Vec::new();
 
         orders.retain(|o| !o.is_delivered());
 
@@ -39,13 +41,36 @@ impl Algorithm for NearestTrainOrderDistributionAlgorithm {
                     orders_to_deliver.reverse();
 
                     let mut nearest_train = None;
+                    let mut route = vec![];
 
                     while nearest_train.is_none() && !orders_to_deliver.is_empty() {
                         let total_weight =
                             orders_to_deliver.iter().map(|o| o.weight()).sum::<u32>();
 
-                        nearest_train =
-                            find_nearest_train(&distance, &trains, location, total_weight);
+                        let destinations = orders_to_deliver
+                            .iter()
+                            .map(|o| o.destination())
+                            .collect_vec();
+
+                        let length;
+                        (route, length) = calculate_best_route_for_distribution(
+                            &distance,
+                            location,
+                            &destinations,
+                        );
+
+                        // Some destination can't be reached on the way.
+                        nearest_train = if length == UNREACHABLE {
+                            None
+                        } else {
+                            find_nearest_train(
+                                &distance,
+                                &trains,
+                                location,
+                                route.last().unwrap_or(location),
+                                total_weight,
+                            )
+                        };
 
                         if nearest_train.is_none() {
                             orders_to_deliver.pop();
@@ -81,49 +106,57 @@ impl Algorithm for NearestTrainOrderDistributionAlgorithm {
                             train.move_to(location, distance(train.location(), location));
                         }
 
-                        let destinations = orders_to_deliver
-                            .iter()
-                            .map(|o| o.destination())
-                            .collect_vec();
-                        let (route, _) = calculate_best_route_for_distribution(
-                            &distance,
-                            location,
-                            &destinations,
+                        // Wait until all orders are ready for pickup, and load them.
+                        train.wait_until(
+                            orders_to_deliver
+                                .iter()
+                                .map(|o| o.earliest_pickup())
+                                .max()
+                                .unwrap_or_default(),
                         );
+                        train.handle(orders_to_deliver.iter().map(|o| o.handling_time()).sum());
 
                         log::debug!("{location} load orders {order_refs:?}");
                         let mut delivery = orders_to_deliver.clone();
 
-                        for station in &route {
-                            order_refs
-                                .iter()
-                                .filter(|o| &o.destination() == station)
-                                .for_each(|drop_order| {
-                                    let order_names =
-                                        delivery.iter().map(|o| o.name().to_owned()).collect_vec();
-
-                                    log::debug!(
-                                        "TRAIN {} from={} load={:?} to={} unload={:?} distance={}",
-                                        train.name(),
-                                        train.location().name(),
-                                        order_names,
-                                        station.name(),
-                                        order_names,
-                                        distance(train.location(), station)
-                                    );
-                                    moves.push(Move::new(
-                                        train.traveled_time(),
-                                        train.name().to_owned(),
-                                        train.location().name().to_owned(),
-                                        order_names.clone(),
-                                        station.name().to_owned(),
-                                        order_names,
-                                    ));
-                                    log::debug!("{:?}", moves.last().unwrap());
-
-                                    delivery.retain(|o| o != drop_order);
-                                    train.move_to(station, distance(train.location(), station));
-                                });
+                        // A station is visited once, however many orders go there.
+                        for station in route.iter().unique() {
+                            let order_names =
+                                delivery.iter().map(|o| o.name().to_owned()).collect_vec();
+
+                            log::debug!(
+                                "TRAIN {} from={} load={:?} to={} unload={:?} distance={}",
+                                train.name(),
+                                train.location().name(),
+                                order_names,
+                                station.name(),
+                                order_names,
+                                distance(train.location(), station)
+                            );
+                            moves.push(Move::new(
+                                train.traveled_time(),
+                                train.name().to_owned(),
+                                train.location().name().to_owned(),
+                                order_names.clone(),
+                                station.name().to_owned(),
+                                order_names,
+                            ));
+                            log::debug!("{:?}", moves.last().unwrap());
+
+                            train.move_to(station, distance(train.location(), station));
+
+                            // Orders for the station are unloaded once, each one for its handling time.
+                            let dropped;
+                            (dropped, delivery) = delivery
+                                .into_iter()
+                                .partition(|o| &o.destination() == station);
+                            train.handle(dropped.iter().map(|o| o.handling_time()).sum());
+                            late.extend(
+                                dropped
+                                    .iter()
+                                    .filter(|o| o.is_late(train.traveled_time()))
+                                    .map(|o| o.name().to_owned()),
+                            );
                         }
                         assert!(delivery.is_empty(), "Undelivered orders!: {delivery:?}");
 
@@ -160,13 +193,55 @@ impl Algorithm for NearestTrainOrderDistributionAlgorithm {
                 );
             }
         }
+        return_trains(&distance, &mut trains, &mut moves)?;
+
         Ok(Solution::new(
             moves,
             trains.iter().map(|t| t.traveled_time()).max().unwrap(),
-        ))
+        )
+        .with_late(late))
     }
 
     fn sort_sensitive(&self) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::network::Network;
+    use crate::solver::{Algorithm, NearestTrainOrderDistributionAlgorithm, UNREACHABLE};
+    use crate::{verify, Input};
+
+    #[test]
+    fn test_handle_orders_for_the_same_destination_once() {
+        let input = Input::try_from(indoc::indoc! {"
+            2
+            A
+            C
+
+            1
+            E1,A,C,20
+
+            2
+            K1,1,A,C,,,5
+            K2,1,A,C,,,5
+
+            1
+            Q1,6,A
+        "})
+        .expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let sut = NearestTrainOrderDistributionAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Solution");
+
+        // Loading both orders takes 10, the trip 20, and unloading both 10 more.
+        assert_eq!(sut.moves().len(), 1);
+        assert_eq!(sut.total_time(), 40);
+        assert!(sut.late().is_empty());
+        verify(&input, &sut).expect("Valid solution");
+    }
+}
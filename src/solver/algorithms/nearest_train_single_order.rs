@@ -4,7 +4,7 @@ use anyhow::bail;
 
 use crate::model::{Order, Station, Train};
 use crate::output::Move;
-use crate::solver::utils::find_nearest_train;
+use crate::solver::utils::{find_nearest_train, return_trains};
 use crate::solver::Algorithm;
 use crate::Solution;
 
@@ -21,6 +21,7 @@ impl Algorithm for NearestTrainSingleOrderAlgorithm {
         distance: &dyn Fn(&Station, &Station) -> u32,
     ) -> anyhow::Result<Solution> {
         let mut moves = Vec::new();
+        let mut late = Vec::new();
 
         let mut orders = VecDeque::from(orders);
         let mut trains = trains;
@@ -45,9 +46,13 @@ impl Algorithm for NearestTrainSingleOrderAlgorithm {
                 continue;
             }
 
-            if let Some((idx, available_at)) =
-                find_nearest_train(&distance, &trains, &pickup_station, order.weight())
-            {
+            if let Some((idx, available_at)) = find_nearest_train(
+                &distance,
+                &trains,
+                &pickup_station,
+                &destination_station,
+                order.weight(),
+            ) {
                 // Remove train from the idle pool.
                 let mut train = trains.remove(idx);
 
@@ -78,6 +83,10 @@ impl Algorithm for NearestTrainSingleOrderAlgorithm {
                     train.move_to(&pickup_station, distance)
                 }
 
+                // Wait until the order is ready for pickup, and load it.
+                train.wait_until(order.earliest_pickup());
+                train.handle(order.handling_time());
+
                 // Pick up order, move to destination, drop order.
                 log::debug!(
                     "ORDER {} delivered from {} to {} by {}, departure={}, arrival={}",
@@ -86,7 +95,7 @@ impl Algorithm for NearestTrainSingleOrderAlgorithm {
                     destination_station.name(),
                     train.name(),
                     train.traveled_time(),
-                    train.traveled_time() + delivery_distance
+                    train.traveled_time() + train.travel_time(delivery_distance)
                 );
 
                 // Move train to location.
@@ -101,22 +110,30 @@ impl Algorithm for NearestTrainSingleOrderAlgorithm {
                 log::debug!("{:?}", moves.last().unwrap());
 
                 train.move_to(&destination_station, delivery_distance);
+                train.handle(order.handling_time());
+
+                if order.is_late(train.traveled_time()) {
+                    late.push(order.name().to_owned());
+                }
 
                 // Return train to the idle pool with updated time.
                 trains.push(train);
             } else {
                 bail!(
-                "There is no train that can deliver an order because it is too big, order={}, weight={}",
+                "There is no train that can deliver an order because it is too big or out of reach, order={}, weight={}",
                 order.name(),
                 order.weight()
             );
             }
         }
 
+        return_trains(&distance, &mut trains, &mut moves)?;
+
         Ok(Solution::new(
             moves,
             trains.iter().map(Train::traveled_time).max().unwrap(),
-        ))
+        )
+        .with_late(late))
     }
 
     fn sort_sensitive(&self) -> bool {
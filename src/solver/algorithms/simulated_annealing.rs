@@ -0,0 +1,336 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::model::{Order, Station, Train};
+use crate::solver::{
+    Algorithm, Budget, LocalSearch, Measure, NearestTrainSingleOrderAlgorithm, Objective,
+};
+use crate::Solution;
+
+/// How the temperature falls over the iterations of simulated annealing.
+/// Temperatures are fractions of the cost of the initial solution,
+/// so a worse solution by `temperature` of it is accepted with the probability of 1/e.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoolingSchedule {
+    /// Starts at `initial`, and multiplies the temperature by `factor` every iteration.
+    Geometric { initial: f64, factor: f64 },
+    /// Starts at `initial`, and lowers the temperature evenly to zero by the last iteration.
+    Linear { initial: f64 },
+}
+
+impl CoolingSchedule {
+    /// The temperature at `iteration` out of `iterations`.
+    pub fn temperature(&self, iteration: usize, iterations: usize) -> f64 {
+        match *self {
+            CoolingSchedule::Geometric { initial, factor } => {
+                initial * factor.powi(iteration as i32)
+            }
+            CoolingSchedule::Linear { initial } => {
+                initial * (1.0 - iteration as f64 / iterations.max(1) as f64)
+            }
+        }
+    }
+}
+
+impl Default for CoolingSchedule {
+    fn default() -> Self {
+        CoolingSchedule::Geometric {
+            initial: INITIAL_TEMPERATURE,
+            factor: COOLING_FACTOR,
+        }
+    }
+}
+
+const INITIAL_TEMPERATURE: f64 = 0.05;
+const COOLING_FACTOR: f64 = 0.999;
+
+/// Parses `geometric=<initial>,<factor>` or `linear=<initial>`, e.g. `geometric=0.1,0.995`.
+/// Omitted numbers are those of the default schedule.
+impl FromStr for CoolingSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, numbers) = s.split_once('=').unwrap_or((s, ""));
+        let numbers = numbers
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| {
+                n.parse::<f64>()
+                    .map_err(|e| format!("Invalid number `{n}`: {e}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let schedule = match (kind.trim(), numbers.as_slice()) {
+            ("geometric", &[]) => CoolingSchedule::default(),
+            ("geometric", &[initial]) => CoolingSchedule::Geometric {
+                initial,
+                factor: COOLING_FACTOR,
+            },
+            ("geometric", &[initial, factor]) => CoolingSchedule::Geometric { initial, factor },
+            ("linear", &[]) => CoolingSchedule::Linear {
+                initial: INITIAL_TEMPERATURE,
+            },
+            ("linear", &[initial]) => CoolingSchedule::Linear { initial },
+            ("geometric" | "linear", _) => return Err(format!("Too many numbers in `{s}`")),
+            (kind, _) => {
+                return Err(format!(
+                    "Unknown cooling schedule `{kind}`, expected one of: geometric, linear"
+                ))
+            }
+        };
+
+        let (CoolingSchedule::Geometric { initial, .. } | CoolingSchedule::Linear { initial }) =
+            schedule;
+        if !initial.is_finite() || initial < 0.0 {
+            return Err(format!(
+                "Invalid initial temperature `{initial}`: must not be negative"
+            ));
+        }
+        if let CoolingSchedule::Geometric { factor, .. } = schedule {
+            if !(factor > 0.0 && factor <= 1.0) {
+                return Err(format!(
+                    "Invalid cooling factor `{factor}`: must be above 0 and at most 1"
+                ));
+            }
+        }
+
+        Ok(schedule)
+    }
+}
+
+impl Display for CoolingSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoolingSchedule::Geometric { initial, factor } => {
+                write!(f, "geometric={initial},{factor}")
+            }
+            CoolingSchedule::Linear { initial } => write!(f, "linear={initial}"),
+        }
+    }
+}
+
+/// Starts from a solution of a greedy `construction`, and changes it at random the way local search does:
+/// relocates or swaps orders, reverses or shifts parts of routes. Worse solutions are accepted
+/// while it is hot, so the search gets out of local optima greedy algorithms get stuck in.
+/// Solutions are ranked by the objective, the makespan by default.
+/// The walk stops early once the budget is over.
+#[derive(Debug, Clone)]
+pub struct SimulatedAnnealingAlgorithm<'a> {
+    construction: &'a dyn Algorithm,
+    objective: &'a dyn Objective,
+    schedule: CoolingSchedule,
+    iterations: usize,
+    seed: u64,
+    budget: Budget,
+}
+
+impl<'a> SimulatedAnnealingAlgorithm<'a> {
+    pub fn new(construction: &'a dyn Algorithm) -> Self {
+        Self {
+            construction,
+            objective: &Measure::Makespan,
+            schedule: CoolingSchedule::default(),
+            iterations: 10_000,
+            seed: rand::random(),
+            budget: Budget::default(),
+        }
+    }
+
+    pub fn with_objective(self, objective: &'a dyn Objective) -> Self {
+        Self { objective, ..self }
+    }
+
+    pub fn with_schedule(self, schedule: CoolingSchedule) -> Self {
+        Self { schedule, ..self }
+    }
+
+    pub fn with_iterations(self, iterations: usize) -> Self {
+        Self { iterations, ..self }
+    }
+
+    /// Sets the seed of random changes, so the walk can be reproduced. A random one by default.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// The seed of random changes, to replay the walk with [`SimulatedAnnealingAlgorithm::with_seed`].
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn with_budget(self, budget: Budget) -> Self {
+        Self { budget, ..self }
+    }
+}
+
+impl Default for SimulatedAnnealingAlgorithm<'_> {
+    fn default() -> Self {
+        Self::new(&NearestTrainSingleOrderAlgorithm)
+    }
+}
+
+impl Algorithm for SimulatedAnnealingAlgorithm<'_> {
+    fn solve(
+        &self,
+        orders: Vec<Order>,
+        trains: Vec<Train>,
+        distance: &dyn Fn(&Station, &Station) -> u32,
+    ) -> anyhow::Result<Solution> {
+        let initial = self
+            .construction
+            .solve(orders.clone(), trains.clone(), distance)?;
+
+        Ok(LocalSearch::new(&orders, &trains, distance)
+            .with_objective(self.objective)
+            .with_budget(self.budget.clone())
+            .anneal(
+                &initial,
+                self.schedule,
+                self.iterations,
+                &mut StdRng::seed_from_u64(self.seed),
+            ))
+    }
+
+    fn sort_sensitive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::Network;
+    use crate::solver::algorithms::simulated_annealing::{
+        CoolingSchedule, SimulatedAnnealingAlgorithm,
+    };
+    use crate::solver::{Algorithm, Measure, NearestTrainSingleOrderAlgorithm, UNREACHABLE};
+    use crate::timeline::Timeline;
+    use crate::{verify, Input, Solution};
+
+    #[test]
+    fn test_cooling_schedule() {
+        let geometric = CoolingSchedule::Geometric {
+            initial: 0.5,
+            factor: 0.5,
+        };
+        assert_eq!(geometric.temperature(0, 10), 0.5);
+        assert_eq!(geometric.temperature(2, 10), 0.125);
+
+        let linear = CoolingSchedule::Linear { initial: 0.5 };
+        assert_eq!(linear.temperature(0, 10), 0.5);
+        assert_eq!(linear.temperature(5, 10), 0.25);
+        assert_eq!(linear.temperature(10, 10), 0.0);
+    }
+
+    #[test]
+    fn test_parse_cooling_schedule() {
+        assert_eq!(
+            "geometric".parse::<CoolingSchedule>(),
+            Ok(CoolingSchedule::default())
+        );
+        assert_eq!(
+            "geometric=0.1, 0.995".parse::<CoolingSchedule>(),
+            Ok(CoolingSchedule::Geometric {
+                initial: 0.1,
+                factor: 0.995
+            })
+        );
+        assert_eq!(
+            "linear=0.2".parse::<CoolingSchedule>(),
+            Ok(CoolingSchedule::Linear { initial: 0.2 })
+        );
+        assert_eq!(
+            "exponential".parse::<CoolingSchedule>(),
+            Err(
+                "Unknown cooling schedule `exponential`, expected one of: geometric, linear"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            "linear=0.1,0.9".parse::<CoolingSchedule>(),
+            Err("Too many numbers in `linear=0.1,0.9`".to_owned())
+        );
+        assert_eq!(
+            "geometric=0.1,1.5".parse::<CoolingSchedule>(),
+            Err("Invalid cooling factor `1.5`: must be above 0 and at most 1".to_owned())
+        );
+        assert_eq!(
+            "linear=-1".parse::<CoolingSchedule>(),
+            Err("Invalid initial temperature `-1`: must not be negative".to_owned())
+        );
+        assert_eq!(
+            CoolingSchedule::default().to_string(),
+            "geometric=0.05,0.999"
+        );
+    }
+
+    #[test]
+    fn test_improve_greedy_solution() {
+        for data in [
+            include_str!("../../data/one_way.txt"),
+            include_str!("../../data/generated.large.2.txt"),
+        ] {
+            let input = Input::try_from(data).expect("Test input");
+            let network = Network::from(&input);
+            let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+            let greedy = NearestTrainSingleOrderAlgorithm
+                .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+                .expect("Greedy solution");
+            let sut = SimulatedAnnealingAlgorithm::default()
+                .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+                .expect("Annealed solution");
+
+            assert!(sut.total_time() < greedy.total_time());
+            verify(&input, &sut).expect("Valid solution");
+        }
+    }
+
+    #[test]
+    fn test_anneal_for_objective() {
+        let input =
+            Input::try_from(include_str!("../../data/generated.large.2.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+        let train_time = |solution: &Solution| {
+            Timeline::new(input.orders(), input.trains(), &distance, solution.moves()).train_time()
+        };
+
+        let greedy = NearestTrainSingleOrderAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Greedy solution");
+        let sut = SimulatedAnnealingAlgorithm::default()
+            .with_objective(&Measure::TrainTime)
+            .with_iterations(1000)
+            .with_seed(0)
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Annealed solution");
+
+        assert!(train_time(&sut) < train_time(&greedy));
+        verify(&input, &sut).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_replay_with_seed() {
+        let input =
+            Input::try_from(include_str!("../../data/generated.large.2.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let annealing = SimulatedAnnealingAlgorithm::default().with_iterations(1000);
+        let solve = |annealing: &SimulatedAnnealingAlgorithm| {
+            annealing
+                .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+                .expect("Annealed solution")
+        };
+
+        // A random seed is known, so the walk can be replayed.
+        let replay = SimulatedAnnealingAlgorithm::default()
+            .with_iterations(1000)
+            .with_seed(annealing.seed());
+        assert_eq!(solve(&replay), solve(&annealing));
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// When searches stop and return the best solution found so far: at a deadline, or once cancelled.
+/// Clones share the cancellation, so a clone kept elsewhere cancels a solve running on other threads.
+/// The default budget is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Budget {
+    /// A budget that runs out `time` from now.
+    pub fn time(time: Duration) -> Self {
+        Self {
+            deadline: Some(Instant::now() + time),
+            ..Default::default()
+        }
+    }
+
+    /// Stops every search with this budget or its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn has_deadline(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Whether the deadline has passed or the budget is cancelled.
+    pub fn is_over(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+
+use crate::model::Order;
+use crate::solver::{Budget, OrderSorter, Rank};
+use crate::Solution;
+
+/// Genetic search over orders fed to a sort-sensitive algorithm.
+/// The first generation is made of orders of other sorters and random permutations,
+/// the next ones of the best permutations carried over (elitism), and children of permutations
+/// picked by tournaments, combined with the order crossover and mutated by swapping two orders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvolveOrders {
+    /// Permutations in every generation.
+    pub population: usize,
+    pub generations: usize,
+    /// Probability of a child to have two orders swapped.
+    pub mutation_rate: f64,
+    /// The best permutations that go to the next generation unchanged.
+    pub elite: usize,
+}
+
+impl Default for EvolveOrders {
+    fn default() -> Self {
+        Self {
+            population: 20,
+            generations: 10,
+            mutation_rate: 0.3,
+            elite: 2,
+        }
+    }
+}
+
+/// A permutation of orders with the solution it decodes to.
+#[derive(Clone)]
+struct Individual {
+    permutation: Vec<usize>,
+    solution: Solution,
+    rank: Rank,
+}
+
+impl EvolveOrders {
+    /// Checks that crossover has two parents to pick from, the elite fits in the population,
+    /// and the mutation rate is a probability.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.population < 2 {
+            bail!(
+                "Evolution population must be at least 2, got {}",
+                self.population
+            );
+        }
+        if self.elite > self.population {
+            bail!(
+                "Evolution elite must not exceed the population of {}, got {}",
+                self.population,
+                self.elite
+            );
+        }
+        if !(0.0..=1.0).contains(&self.mutation_rate) {
+            bail!(
+                "Evolution mutation rate must be between 0 and 1, got {}",
+                self.mutation_rate
+            );
+        }
+        Ok(())
+    }
+
+    /// Permutations of `orders` orders decoded by a search that is not stopped early:
+    /// the first generation, and children of every next one.
+    pub(crate) fn evaluations(&self, orders: usize) -> usize {
+        let children = if orders > 1 {
+            self.generations
+                * self
+                    .population
+                    .saturating_sub(self.elite.min(self.population))
+        } else {
+            0
+        };
+        self.population.max(1) + children
+    }
+
+    /// Evolves permutations of `orders`, starting from ones of `sorters`, and returns the best solution.
+    /// `decode` solves and ranks orders in the given order, `rng` makes every random choice.
+    /// Once `budget` is over, no more permutations are decoded, except the first one.
+    pub fn evolve(
+        &self,
+        orders: &[Order],
+        sorters: &[&dyn OrderSorter],
+        mut rng: &mut dyn RngCore,
+        budget: &Budget,
+        mut decode: impl FnMut(Vec<Order>) -> (Solution, Rank),
+    ) -> Solution {
+        let indices: HashMap<&str, usize> = orders
+            .iter()
+            .enumerate()
+            .map(|(index, order)| (order.name(), index))
+            .collect();
+        let permutation = |sorter: &dyn OrderSorter, rng: &mut dyn RngCore| {
+            sorter
+                .sort(orders, rng)
+                .iter()
+                .map(|order| indices[order.name()])
+                .collect_vec()
+        };
+
+        let mut evaluate = |permutation: Vec<usize>| {
+            let (solution, rank) = decode(permutation.iter().map(|&i| orders[i].clone()).collect());
+            Individual {
+                permutation,
+                solution,
+                rank,
+            }
+        };
+
+        let mut seeds = sorters
+            .iter()
+            .filter(|sorter| sorter.stable())
+            .map(|sorter| permutation(*sorter, rng))
+            .unique()
+            .take(self.population.max(1))
+            .collect_vec();
+        while seeds.len() < self.population {
+            seeds.push(permutation(self, rng));
+        }
+
+        let mut seeds = seeds.into_iter();
+        let mut population = seeds.next().map(&mut evaluate).into_iter().collect_vec();
+        population.extend(seeds.take_while(|_| !budget.is_over()).map(&mut evaluate));
+
+        // There is nothing to evolve with fewer than two orders.
+        if orders.len() > 1 {
+            for _ in 0..self.generations {
+                if budget.is_over() {
+                    break;
+                }
+
+                population.sort_by_key(|individual| individual.rank);
+
+                let mut next = population
+                    .iter()
+                    .take(self.elite.min(self.population))
+                    .cloned()
+                    .collect_vec();
+
+                while next.len() < self.population && !budget.is_over() {
+                    let first = tournament(&population, &mut rng);
+                    let second = tournament(&population, &mut rng);
+
+                    let mut child =
+                        order_crossover(&first.permutation, &second.permutation, &mut rng);
+                    if rng.gen_bool(self.mutation_rate) {
+                        let (a, b) = (rng.gen_range(0..child.len()), rng.gen_range(0..child.len()));
+                        child.swap(a, b);
+                    }
+
+                    next.push(evaluate(child));
+                }
+
+                // Individuals of an unfinished generation compete with the last one.
+                if next.len() < self.population {
+                    population.extend(next.into_iter().skip(self.elite.min(self.population)));
+                } else {
+                    population = next;
+                }
+            }
+        }
+
+        population
+            .into_iter()
+            .min_by_key(|individual| individual.rank)
+            .map(|individual| individual.solution)
+            .unwrap_or_else(|| Solution::new(vec![], u32::MAX))
+    }
+}
+
+impl OrderSorter for EvolveOrders {
+    /// A random permutation, like ones the first generation is filled with.
+    fn sort(&self, orders: &[Order], rng: &mut dyn RngCore) -> Vec<Order> {
+        let mut v = orders.to_vec();
+        v.shuffle(rng);
+        v
+    }
+
+    fn stable(&self) -> bool {
+        false
+    }
+}
+
+/// The better of two random individuals.
+fn tournament<'p>(population: &'p [Individual], rng: &mut impl Rng) -> &'p Individual {
+    let first = population.choose(rng).expect("Non-empty population");
+    let second = population.choose(rng).expect("Non-empty population");
+    if second.rank < first.rank {
+        second
+    } else {
+        first
+    }
+}
+
+/// Order crossover (OX): the child takes a random slice of `first` in place,
+/// and the rest of positions after the slice, wrapping around, in the order of `second`.
+fn order_crossover(first: &[usize], second: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+    let len = first.len();
+    let start = rng.gen_range(0..len);
+    let end = rng.gen_range(start..len);
+
+    let slice = &first[start..=end];
+    let mut rest = second
+        .iter()
+        .cycle()
+        .skip(end + 1)
+        .take(len)
+        .filter(|gene| !slice.contains(gene));
+
+    let mut child = vec![0; len];
+    for offset in 1..=len {
+        let position = (end + offset) % len;
+        child[position] = if (start..=end).contains(&position) {
+            first[position]
+        } else {
+            *rest.next().expect("Gene for every position")
+        };
+    }
+
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::network::Network;
+    use crate::solver::evolution::{order_crossover, EvolveOrders};
+    use crate::solver::{
+        Algorithm, Budget, DoNotSortOrders, Measure, NearestTrainSingleOrderAlgorithm, Rank,
+        UNREACHABLE,
+    };
+    use crate::timeline::Timeline;
+    use crate::{verify, Input};
+
+    #[test]
+    fn test_order_crossover() {
+        let first = (0..8).collect_vec();
+        let second = vec![7, 6, 5, 4, 3, 2, 1, 0];
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let child = order_crossover(&first, &second, &mut rng);
+
+            // Every order is kept once, and the slice from the first parent stays in place.
+            assert_eq!(child.iter().copied().sorted().collect_vec(), first);
+            let kept = child.iter().zip(&first).filter(|(c, f)| c == f).count();
+            assert!(kept >= 1);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(EvolveOrders::default().validate().is_ok());
+
+        let invalid = [
+            (
+                EvolveOrders {
+                    population: 0,
+                    elite: 0,
+                    ..Default::default()
+                },
+                "Evolution population must be at least 2, got 0",
+            ),
+            (
+                EvolveOrders {
+                    population: 1,
+                    elite: 0,
+                    ..Default::default()
+                },
+                "Evolution population must be at least 2, got 1",
+            ),
+            (
+                EvolveOrders {
+                    population: 2,
+                    elite: 3,
+                    ..Default::default()
+                },
+                "Evolution elite must not exceed the population of 2, got 3",
+            ),
+            (
+                EvolveOrders {
+                    mutation_rate: 1.5,
+                    ..Default::default()
+                },
+                "Evolution mutation rate must be between 0 and 1, got 1.5",
+            ),
+        ];
+        for (evolution, error) in invalid {
+            assert_eq!(
+                evolution
+                    .validate()
+                    .expect_err("Invalid evolution")
+                    .to_string(),
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_evolve_orders() {
+        let input =
+            Input::try_from(include_str!("../data/generated.large.2.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let unsorted = NearestTrainSingleOrderAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Unsorted solution");
+
+        let mut evaluations = 0;
+        let mut rng = StdRng::seed_from_u64(0);
+        let sut = EvolveOrders::default().evolve(
+            input.orders(),
+            &[&DoNotSortOrders],
+            &mut rng,
+            &Budget::default(),
+            |orders| {
+                evaluations += 1;
+                let solution = NearestTrainSingleOrderAlgorithm
+                    .solve(orders, input.trains().to_vec(), &distance)
+                    .expect("Solution");
+                let timeline =
+                    Timeline::new(input.orders(), input.trains(), &distance, solution.moves());
+                let rank = Rank::new(&Measure::Makespan, &solution, &timeline);
+                (solution, rank)
+            },
+        );
+
+        // The unsorted orders are in the first generation, so the result is no worse.
+        assert_eq!(evaluations, 20 + 10 * 18);
+        assert_eq!(
+            EvolveOrders::default().evaluations(input.orders().len()),
+            evaluations
+        );
+        assert!(sut.total_time() <= unsorted.total_time());
+        verify(&input, &sut).expect("Valid solution");
+    }
+
+    #[test]
+    fn test_evolve_orders_over_budget() {
+        let input =
+            Input::try_from(include_str!("../data/generated.large.2.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let budget = Budget::default();
+        budget.cancel();
+
+        let mut evaluations = 0;
+        let mut rng = StdRng::seed_from_u64(0);
+        let sut = EvolveOrders::default().evolve(
+            input.orders(),
+            &[&DoNotSortOrders],
+            &mut rng,
+            &budget,
+            |orders| {
+                evaluations += 1;
+                let solution = NearestTrainSingleOrderAlgorithm
+                    .solve(orders, input.trains().to_vec(), &distance)
+                    .expect("Solution");
+                let timeline =
+                    Timeline::new(input.orders(), input.trains(), &distance, solution.moves());
+                let rank = Rank::new(&Measure::Makespan, &solution, &timeline);
+                (solution, rank)
+            },
+        );
+
+        // Only the first permutation is decoded, so there is still a solution.
+        assert_eq!(evaluations, 1);
+        verify(&input, &sut).expect("Valid solution");
+    }
+}
@@ -0,0 +1,473 @@
+use itertools::Itertools;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+
+use crate::model::{Order, Station, Train};
+use crate::output::Move;
+use crate::solver::route::{Route, Router, Visit};
+use crate::solver::{Budget, CoolingSchedule, Measure, Objective, Rank};
+use crate::timeline::Timeline;
+use crate::Solution;
+
+/// Improves a solution with neighbourhood moves until no move makes it better:
+/// relocating an order to another place or train, swapping orders between trains,
+/// reversing a part of a route (2-opt), and moving a few consecutive stops elsewhere in a route (or-opt).
+///
+/// Solutions are ranked by the objective, ties are broken by the time trains work,
+/// so orders keep moving off the busiest train even when the objective does not change yet.
+/// Once the budget is over, the best solution found so far is returned.
+pub struct LocalSearch<'l> {
+    router: Router<'l>,
+    objective: &'l dyn Objective,
+    budget: Budget,
+}
+
+/// New routes of some trains.
+type Change = Vec<(usize, Route)>;
+
+type Cost = (Rank, u64);
+
+/// Routes of all trains with their timeline.
+#[derive(Clone)]
+struct Plan<'p> {
+    routes: Vec<Route>,
+    timeline: Timeline<'p>,
+    cost: Cost,
+}
+
+impl<'l> LocalSearch<'l> {
+    pub fn new(
+        orders: &'l [Order],
+        trains: &'l [Train],
+        distance: &'l dyn Fn(&Station, &Station) -> u32,
+    ) -> Self {
+        Self {
+            router: Router::new(orders, trains, distance),
+            objective: &Measure::Makespan,
+            budget: Budget::default(),
+        }
+    }
+
+    /// Sets the objective solutions are ranked by, the makespan by default.
+    pub fn with_objective(self, objective: &'l dyn Objective) -> Self {
+        Self { objective, ..self }
+    }
+
+    /// Sets when the search stops, it runs until no move improves the solution by default.
+    pub fn with_budget(self, budget: Budget) -> Self {
+        Self { budget, ..self }
+    }
+
+    /// Returns an improved `solution`, or the same one if no move improves it.
+    /// Moves of orders that are not known are kept as they are, by keeping the whole solution.
+    pub fn improve(&self, solution: &Solution) -> Solution {
+        let Some(mut plan) = self
+            .router
+            .routes(solution)
+            .and_then(|routes| self.plan(routes))
+        else {
+            return solution.clone();
+        };
+
+        while let Some(better) = self.find_improvement(&plan) {
+            plan = better;
+        }
+
+        self.solution(&plan, solution)
+    }
+
+    /// Walks from `solution` through random changes for `iterations`, accepting worse ones
+    /// with the probability that falls as `schedule` cools down, and returns the best solution seen.
+    /// Temperatures are relative to the cost of `solution`, and every late order costs as much as the makespan.
+    pub fn anneal(
+        &self,
+        solution: &Solution,
+        schedule: CoolingSchedule,
+        iterations: usize,
+        rng: &mut impl Rng,
+    ) -> Solution {
+        let Some(mut current) = self
+            .router
+            .routes(solution)
+            .and_then(|routes| self.plan(routes))
+        else {
+            return solution.clone();
+        };
+        let mut best = current.clone();
+
+        let mut energy = self.energy(&current.timeline);
+        let scale = energy.max(1.0);
+
+        for iteration in 0..iterations {
+            if self.budget.is_over() {
+                break;
+            }
+
+            let Some(change) = self.random_change(&current.routes, rng) else {
+                continue;
+            };
+            let Some(timeline) = self.timeline(&current.timeline, &change) else {
+                continue;
+            };
+
+            let next_energy = self.energy(&timeline);
+            let temperature = schedule.temperature(iteration, iterations) * scale;
+            let accepted = next_energy <= energy
+                || temperature > 0.0
+                    && rng.gen::<f64>() < ((energy - next_energy) / temperature).exp();
+            if !accepted {
+                continue;
+            }
+
+            for (train, route) in change {
+                current.routes[train] = route;
+            }
+            current.cost = self.cost(&timeline);
+            current.timeline = timeline;
+            energy = next_energy;
+
+            if current.cost < best.cost {
+                best = current.clone();
+            }
+        }
+
+        self.solution(&best, solution)
+    }
+
+    /// The solution made of `plan`, if it is better than `original`, or `original` otherwise.
+    fn solution(&self, plan: &Plan, original: &Solution) -> Solution {
+        let Some(moves) = self.router.moves(&plan.routes) else {
+            return original.clone();
+        };
+
+        let (solution, cost) = self.evaluate(moves);
+        if cost < self.evaluate(original.moves().to_vec()).1 {
+            solution
+        } else {
+            original.clone()
+        }
+    }
+
+    fn plan(&self, routes: Vec<Route>) -> Option<Plan<'l>> {
+        let change = routes.iter().cloned().enumerate().collect_vec();
+        let timeline = self.timeline(
+            &Timeline::new(
+                self.router.orders,
+                self.router.trains,
+                self.router.distance,
+                &[],
+            ),
+            &change,
+        )?;
+
+        Some(Plan {
+            routes,
+            cost: self.cost(&timeline),
+            timeline,
+        })
+    }
+
+    /// The plan after the first change of routes that makes `plan` better.
+    fn find_improvement(&self, plan: &Plan<'l>) -> Option<Plan<'l>> {
+        self.relocations(&plan.routes)
+            .chain(self.swaps(&plan.routes))
+            .chain(self.reversals(&plan.routes))
+            .chain(self.shifts(&plan.routes))
+            .take_while(|_| !self.budget.is_over())
+            .find_map(|change| {
+                let timeline = self.timeline(&plan.timeline, &change)?;
+                let cost = self.cost(&timeline);
+                if cost >= plan.cost {
+                    return None;
+                }
+
+                let mut routes = plan.routes.clone();
+                for (train, route) in change {
+                    routes[train] = route;
+                }
+
+                Some(Plan {
+                    routes,
+                    timeline,
+                    cost,
+                })
+            })
+    }
+
+    /// `timeline` after trains change their routes, `None` if a new route is not feasible.
+    fn timeline(&self, timeline: &Timeline<'l>, change: &[(usize, Route)]) -> Option<Timeline<'l>> {
+        let mut timeline = timeline.clone();
+
+        for (train, route) in change {
+            let schedule = self.router.simulate(*train, route, None)?;
+
+            timeline.set_finished_at(self.router.trains[*train].name(), schedule.finished_at);
+            for (order, time) in schedule.delivered_at {
+                timeline.set_delivered_at(self.router.orders[order].name(), time);
+            }
+        }
+
+        Some(timeline)
+    }
+
+    /// Moves every order to every place in every train, its delivery after its pickup.
+    fn relocations<'r>(&'r self, routes: &'r [Route]) -> impl Iterator<Item = Change> + 'r {
+        orders(routes).flat_map(move |(from, order)| {
+            (0..routes.len())
+                .filter(move |&to| {
+                    self.router.trains[to].capacity() >= self.router.orders[order].weight()
+                })
+                .flat_map(move |to| {
+                    // The order leaves its own route before it is placed again.
+                    let len = match to == from {
+                        true => routes[to].len() - 2,
+                        false => routes[to].len(),
+                    };
+
+                    (0..=len)
+                        .flat_map(move |pickup| {
+                            (pickup..=len).map(move |delivery| (pickup, delivery))
+                        })
+                        .map(move |(pickup, delivery)| {
+                            relocated(routes, (from, order), to, pickup, delivery)
+                        })
+                })
+        })
+    }
+
+    /// Exchanges orders of two trains, each taking the places of the other in the route.
+    fn swaps<'r>(&'r self, routes: &'r [Route]) -> impl Iterator<Item = Change> + 'r {
+        orders(routes)
+            .tuple_combinations()
+            .filter(|((a, _), (b, _))| a != b)
+            .map(|(first, second)| swapped(routes, first, second))
+    }
+
+    /// Reverses every part of every route (2-opt).
+    fn reversals<'r>(&'r self, routes: &'r [Route]) -> impl Iterator<Item = Change> + 'r {
+        routes.iter().enumerate().flat_map(|(train, route)| {
+            (0..route.len())
+                .tuple_combinations()
+                .map(move |(start, end)| vec![(train, reversed(route, start, end))])
+        })
+    }
+
+    /// Moves up to three consecutive visits to every other place in the route (or-opt).
+    fn shifts<'r>(&'r self, routes: &'r [Route]) -> impl Iterator<Item = Change> + 'r {
+        routes.iter().enumerate().flat_map(|(train, route)| {
+            (1..=3)
+                .flat_map(move |length| {
+                    (0..(route.len() + 1).saturating_sub(length)).map(move |start| (start, length))
+                })
+                .flat_map(move |(start, length)| {
+                    (0..=route.len() - length)
+                        .filter(move |&position| position != start)
+                        .map(move |position| vec![(train, shifted(route, start, length, position))])
+                })
+        })
+    }
+
+    /// A random change of one of the kinds local search makes, `None` if the picked kind
+    /// does not apply, e.g. there is no other train to swap orders with.
+    fn random_change(&self, routes: &[Route], rng: &mut impl Rng) -> Option<Change> {
+        let orders = orders(routes).collect_vec();
+
+        match rng.gen_range(0..4) {
+            0 => {
+                let &(from, order) = orders.choose(rng)?;
+                let to = rng.gen_range(0..routes.len());
+                if self.router.trains[to].capacity() < self.router.orders[order].weight() {
+                    return None;
+                }
+
+                let len = match to == from {
+                    true => routes[to].len() - 2,
+                    false => routes[to].len(),
+                };
+                let pickup = rng.gen_range(0..=len);
+                let delivery = rng.gen_range(pickup..=len);
+
+                Some(relocated(routes, (from, order), to, pickup, delivery))
+            }
+            1 => {
+                let (first, second) = (*orders.choose(rng)?, *orders.choose(rng)?);
+                (first.0 != second.0).then(|| swapped(routes, first, second))
+            }
+            2 => {
+                let (train, route) = routes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.len() > 1)
+                    .choose(rng)?;
+                let start = rng.gen_range(0..route.len() - 1);
+                let end = rng.gen_range(start + 1..route.len());
+
+                Some(vec![(train, reversed(route, start, end))])
+            }
+            _ => {
+                let (train, route) = routes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.len() > 1)
+                    .choose(rng)?;
+                let length = rng.gen_range(1..=3.min(route.len() - 1));
+                let start = rng.gen_range(0..=route.len() - length);
+                let position = rng.gen_range(0..=route.len() - length);
+
+                (position != start).then(|| vec![(train, shifted(route, start, length, position))])
+            }
+        }
+    }
+
+    fn cost(&self, timeline: &Timeline) -> Cost {
+        let ranked = Solution::new(vec![], timeline.makespan()).with_late(self.late(timeline));
+        (
+            Rank::new(self.objective, &ranked, timeline),
+            timeline.train_time(),
+        )
+    }
+
+    /// The objective cost with late orders costing as much as the makespan each,
+    /// so the walk can trade lateness for time.
+    fn energy(&self, timeline: &Timeline) -> f64 {
+        let late = self.late(timeline).len();
+        self.objective.cost(timeline) + late as f64 * timeline.makespan() as f64
+    }
+
+    fn late(&self, timeline: &Timeline) -> Vec<String> {
+        self.router
+            .orders
+            .iter()
+            .filter(|o| matches!(timeline.delivered_at(o.name()), Some(time) if o.is_late(time)))
+            .map(|o| o.name().to_owned())
+            .collect()
+    }
+
+    /// The solution made of `moves`, and its cost.
+    fn evaluate(&self, moves: Vec<Move>) -> (Solution, Cost) {
+        let timeline = Timeline::new(
+            self.router.orders,
+            self.router.trains,
+            self.router.distance,
+            &moves,
+        );
+        let cost = self.cost(&timeline);
+        let late = self.late(&timeline);
+        let total_time = timeline.makespan();
+
+        (Solution::new(moves, total_time).with_late(late), cost)
+    }
+}
+
+/// Orders picked up in `routes`, with their trains.
+fn orders(routes: &[Route]) -> impl Iterator<Item = (usize, usize)> + Clone + '_ {
+    routes.iter().enumerate().flat_map(|(train, route)| {
+        route.iter().filter_map(move |visit| match visit {
+            Visit::Pickup(order) => Some((train, *order)),
+            Visit::Deliver(_) => None,
+        })
+    })
+}
+
+/// Moves `order` from its train to `pickup` and `delivery` places in the route of `to`,
+/// counted once the order has left its route.
+fn relocated(
+    routes: &[Route],
+    (from, order): (usize, usize),
+    to: usize,
+    pickup: usize,
+    delivery: usize,
+) -> Change {
+    let rest = routes[from]
+        .iter()
+        .filter(|v| v.order() != order)
+        .copied()
+        .collect_vec();
+
+    let mut route = match to == from {
+        true => rest.clone(),
+        false => routes[to].clone(),
+    };
+    route.insert(delivery, Visit::Deliver(order));
+    route.insert(pickup, Visit::Pickup(order));
+
+    match to == from {
+        true => vec![(to, route)],
+        false => vec![(from, rest), (to, route)],
+    }
+}
+
+/// Exchanges two orders of different trains in their routes.
+fn swapped(routes: &[Route], (a, first): (usize, usize), (b, second): (usize, usize)) -> Change {
+    let replace = |route: &Route, from: usize, to: usize| {
+        route
+            .iter()
+            .map(|visit| match *visit {
+                Visit::Pickup(order) if order == from => Visit::Pickup(to),
+                Visit::Deliver(order) if order == from => Visit::Deliver(to),
+                visit => visit,
+            })
+            .collect_vec()
+    };
+
+    vec![
+        (a, replace(&routes[a], first, second)),
+        (b, replace(&routes[b], second, first)),
+    ]
+}
+
+fn reversed(route: &Route, start: usize, end: usize) -> Route {
+    let mut route = route.clone();
+    route[start..=end].reverse();
+    route
+}
+
+/// Moves `length` visits from `start` to `position` in the rest of the route.
+fn shifted(route: &Route, start: usize, length: usize, position: usize) -> Route {
+    let mut route = route.clone();
+    let segment = route.drain(start..start + length).collect_vec();
+    route.splice(position..position, segment);
+    route
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::Network;
+    use crate::solver::{Algorithm, LocalSearch, NearestTrainSingleOrderAlgorithm, UNREACHABLE};
+    use crate::{verify, Input};
+
+    #[test]
+    fn test_improve_greedy_solution() {
+        for (data, greedy_time, improved_time) in [
+            (include_str!("../data/one_way.txt"), 97, 82),
+            (include_str!("../data/generated.large.2.txt"), 477, 383),
+        ] {
+            let input = Input::try_from(data).expect("Test input");
+            let network = Network::from(&input);
+            let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+            let greedy = NearestTrainSingleOrderAlgorithm
+                .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+                .expect("Greedy solution");
+            let sut = LocalSearch::new(input.orders(), input.trains(), &distance).improve(&greedy);
+
+            assert_eq!(greedy.total_time(), greedy_time);
+            assert_eq!(sut.total_time(), improved_time);
+            verify(&input, &sut).expect("Valid solution");
+        }
+    }
+
+    #[test]
+    fn test_keep_optimal_solution() {
+        let input = Input::try_from(include_str!("../data/simple.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let greedy = NearestTrainSingleOrderAlgorithm
+            .solve(input.orders().to_vec(), input.trains().to_vec(), &distance)
+            .expect("Greedy solution");
+        let sut = LocalSearch::new(input.orders(), input.trains(), &distance).improve(&greedy);
+
+        assert_eq!(sut, greedy);
+    }
+}
@@ -1,29 +1,81 @@
 mod algorithms;
+mod budget;
+mod evolution;
+mod local_search;
+mod objective;
 mod order_sorter;
+mod route;
 mod utils;
 
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 use std::time::Instant;
 
 pub use algorithms::*;
+pub use budget::Budget;
+pub use evolution::EvolveOrders;
+use itertools::Itertools;
 use kdam::{tqdm, BarExt};
+pub use local_search::LocalSearch;
+pub use objective::{Measure, Objective, Rank, WeightedObjective};
 pub use order_sorter::OrderSorter;
 pub use order_sorter::*;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+pub use utils::{is_serviceable, UNREACHABLE};
 
 use crate::model::{Order, Station, Train};
+use crate::timeline::Timeline;
 use crate::Solution;
 
 pub struct Solver<'s, F>
 where
-    F: Fn(&Station, &Station) -> u32,
+    F: Fn(&Station, &Station) -> u32 + Sync,
 {
     algorithms: &'s [&'s dyn Algorithm],
     order_sorters: &'s [&'s dyn OrderSorter],
     distance: F,
+    objective: &'s dyn Objective,
+    evolution: Option<&'s EvolveOrders>,
+    workers: usize,
+    seed: u64,
+    budget: Budget,
+    random_iterations: usize,
+}
+
+/// A run of an algorithm, with orders of a sorter or evolved ones if the algorithm is sort-sensitive.
+#[derive(Clone, Copy)]
+enum Job<'s> {
+    Unsorted(&'s dyn Algorithm),
+    Sorted(&'s dyn Algorithm, &'s dyn OrderSorter),
+    Evolved(&'s dyn Algorithm, &'s EvolveOrders),
+}
+
+impl Job<'_> {
+    /// Whether the job sorts orders at random, so runs of it are skipped once the budget is over.
+    fn is_random(&self) -> bool {
+        matches!(self, Job::Sorted(_, order_sorter) if !order_sorter.stable())
+    }
+
+    /// Times the job runs its algorithm on `orders`, at most.
+    fn runs(&self, orders: usize) -> usize {
+        match self {
+            Job::Unsorted(_) | Job::Sorted(..) => 1,
+            Job::Evolved(_, evolution) => evolution.evaluations(orders),
+        }
+    }
+}
+
+/// Threads the machine can run at once, or one if that is not known.
+fn available_workers() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
 }
 
 impl<'s, F> Solver<'s, F>
 where
-    F: Fn(&Station, &Station) -> u32,
+    F: Fn(&Station, &Station) -> u32 + Sync,
 {
     pub fn new(
         algorithms: &'s [&'s dyn Algorithm],
@@ -34,65 +86,228 @@ where
             algorithms,
             order_sorters,
             distance,
+            objective: &Measure::Makespan,
+            evolution: None,
+            workers: available_workers(),
+            seed: rand::random(),
+            budget: Budget::default(),
+            random_iterations: 100,
         }
     }
 
+    /// Sets the seed of random sorters and evolution, so results can be reproduced.
+    /// A random one by default, logged when solving starts.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Sets when random sorters and evolution stop, and results found so far are returned.
+    /// With a deadline, random sorters run again and again until it, instead of a number of iterations.
+    pub fn with_budget(self, budget: Budget) -> Self {
+        Self { budget, ..self }
+    }
+
+    /// Sets how many times random sorters run without a deadline, 100 by default.
+    pub fn with_random_iterations(self, random_iterations: usize) -> Self {
+        Self {
+            random_iterations,
+            ..self
+        }
+    }
+
+    /// Sets how many threads run algorithms at once, as many as the machine runs by default.
+    pub fn with_workers(self, workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the objective results are ranked by, the makespan by default.
+    pub fn with_objective(self, objective: &'s dyn Objective) -> Self {
+        Self { objective, ..self }
+    }
+
+    /// Also evolves orders for sort-sensitive algorithms, after trying the sorters.
+    /// Solving fails if the settings are not valid, see [`EvolveOrders::validate`].
+    pub fn with_evolution(self, evolution: &'s EvolveOrders) -> Self {
+        Self {
+            evolution: Some(evolution),
+            ..self
+        }
+    }
+
+    /// Runs every algorithm, with orders of every sorter if it is sort-sensitive, on `workers` threads.
+    /// Results are ranked by the objective, ties keep the order of algorithms and sorters.
+    /// Every job draws random numbers from its own generator, seeded by the seed and the job index,
+    /// so the same seed gives the same results on any number of threads.
+    ///
+    /// Algorithms with stable sorters, or none, always run, so there are results even if the budget is over.
+    /// Runs with random sorters until a deadline keep only the best result of every worker
+    /// for every algorithm and sorter, and depend on the speed of the machine.
     pub fn solve(
         &self,
         orders: Vec<Order>,
         trains: Vec<Train>,
     ) -> anyhow::Result<Vec<SolverResult>> {
-        let mut pb = tqdm!();
-
-        let mut results = Vec::new();
+        log::debug!("Solving with seed {}", self.seed);
+        if let Some(evolution) = self.evolution {
+            evolution.validate()?;
+        }
 
+        let mut jobs = vec![];
+        // Jobs that run again and again until the deadline, once other jobs are done.
+        let mut repeated = vec![];
         for &algorithm in self.algorithms {
             if algorithm.sort_sensitive() {
                 // Iterate over sorters.
                 for &order_sorter in self.order_sorters {
-                    let iterations = if order_sorter.stable() { 1 } else { 100 };
-                    for _ in 0..iterations {
-                        let now = Instant::now();
-
-                        let solution = algorithm
-                            .solve(order_sorter.sort(&orders), trains.clone(), &self.distance)
-                            .unwrap_or_else(|e| {
-                                log::error!("{algorithm:?} / {order_sorter:?}: {e:#?}");
-                                Solution::new(vec![], u32::MAX)
-                            });
-
-                        results.push(SolverResult {
-                            elapsed: now.elapsed(),
-                            algorithm,
-                            order_sorter: Some(order_sorter),
-                            solution,
-                        });
-                        pb.update(1);
+                    let job = Job::Sorted(algorithm, order_sorter);
+                    if order_sorter.stable() {
+                        jobs.push(job);
+                    } else if self.budget.has_deadline() {
+                        repeated.push(job);
+                    } else {
+                        jobs.extend((0..self.random_iterations).map(|_| job));
                     }
                 }
+
+                if let Some(evolution) = self.evolution {
+                    jobs.push(Job::Evolved(algorithm, evolution));
+                }
             } else {
-                let now = Instant::now();
+                jobs.push(Job::Unsorted(algorithm));
+            }
+        }
 
-                let solution = algorithm
-                    .solve(orders.clone(), trains.clone(), &self.distance)
-                    .unwrap_or_else(|e| {
-                        log::error!("{algorithm:?} / None: {e:#?}");
-                        Solution::new(vec![], u32::MAX)
-                    });
+        // Runs of repeated jobs are not known until the deadline, so the bar only counts them.
+        let pb = if repeated.is_empty() {
+            let total = jobs.iter().map(|job| job.runs(orders.len())).sum::<usize>();
+            Mutex::new(tqdm!(total = total))
+        } else {
+            Mutex::new(tqdm!())
+        };
 
-                results.push(SolverResult {
-                    elapsed: now.elapsed(),
-                    algorithm,
-                    order_sorter: None,
-                    solution,
+        // Workers take the next job until none is left, and keep results with job indices.
+        // Indices past the jobs are runs of repeated jobs, in turn.
+        let next = AtomicUsize::new(0);
+        let mut results = thread::scope(|scope| {
+            let workers = (0..self.workers.min(jobs.len() + repeated.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = vec![];
+                        let mut best = repeated.iter().map(|_| None).collect_vec();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let job = match jobs.get(index) {
+                                Some(&job) => job,
+                                None if !repeated.is_empty() && !self.budget.is_over() => {
+                                    repeated[(index - jobs.len()) % repeated.len()]
+                                }
+                                None => break,
+                            };
+                            if job.is_random() && self.budget.is_over() {
+                                continue;
+                            }
+
+                            let mut rng =
+                                StdRng::seed_from_u64(self.seed.wrapping_add(index as u64));
+                            let result = self.run(job, &orders, &trains, &mut rng, &pb);
+                            let Some(repeat) = index.checked_sub(jobs.len()) else {
+                                results.push((index, result));
+                                continue;
+                            };
+
+                            let rank = self.rank(&orders, &trains, &result.solution);
+                            let best = &mut best[repeat % repeated.len()];
+                            if !matches!(best, Some((_, best_rank, _)) if *best_rank <= rank) {
+                                *best = Some((index, rank, result));
+                            }
+                        }
+
+                        results.extend(
+                            best.into_iter()
+                                .flatten()
+                                .map(|(index, _, result)| (index, result)),
+                        );
+                        results
+                    })
+                })
+                .collect_vec();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("Solver worker"))
+                .collect_vec()
+        });
+        results.sort_by_key(|(index, _)| *index);
+
+        let mut results = results.into_iter().map(|(_, result)| result).collect_vec();
+        results.sort_by_cached_key(|r| self.rank(&orders, &trains, &r.solution));
+        Ok(results)
+    }
+
+    fn run(
+        &self,
+        job: Job<'s>,
+        orders: &[Order],
+        trains: &[Train],
+        rng: &mut dyn RngCore,
+        pb: &Mutex<kdam::Bar>,
+    ) -> SolverResult<'s> {
+        let now = Instant::now();
+
+        let solve = |algorithm: &dyn Algorithm,
+                     orders: Vec<Order>,
+                     order_sorter: Option<&dyn OrderSorter>| {
+            let solution = algorithm
+                .solve(orders, trains.to_vec(), &self.distance)
+                .unwrap_or_else(|e| {
+                    log::error!("{algorithm:?} / {order_sorter:?}: {e:#?}");
+                    Solution::new(vec![], u32::MAX)
                 });
+            pb.lock().expect("Progress bar").update(1);
+            solution
+        };
 
-                pb.update(1);
+        let (algorithm, order_sorter, solution) = match job {
+            Job::Unsorted(algorithm) => (algorithm, None, solve(algorithm, orders.to_vec(), None)),
+            Job::Sorted(algorithm, order_sorter) => (
+                algorithm,
+                Some(order_sorter),
+                solve(
+                    algorithm,
+                    order_sorter.sort(orders, rng),
+                    Some(order_sorter),
+                ),
+            ),
+            Job::Evolved(algorithm, evolution) => {
+                let solution = evolution.evolve(
+                    orders,
+                    self.order_sorters,
+                    rng,
+                    &self.budget,
+                    |permutation| {
+                        let solution = solve(algorithm, permutation, Some(evolution));
+                        let rank = self.rank(orders, trains, &solution);
+                        (solution, rank)
+                    },
+                );
+                (algorithm, Some(evolution as &dyn OrderSorter), solution)
             }
+        };
+
+        SolverResult {
+            elapsed: now.elapsed(),
+            algorithm,
+            order_sorter,
+            solution,
         }
+    }
 
-        results.sort_by_key(|r| r.solution.total_time());
-        Ok(results)
+    fn rank(&self, orders: &[Order], trains: &[Train], solution: &Solution) -> Rank {
+        let timeline = Timeline::new(orders, trains, &self.distance, solution.moves());
+        Rank::new(self.objective, solution, &timeline)
     }
 }
 
@@ -102,3 +317,99 @@ pub struct SolverResult<'s> {
     pub order_sorter: Option<&'s dyn OrderSorter>,
     pub solution: Solution,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use itertools::Itertools;
+
+    use crate::network::Network;
+    use crate::solver::{
+        Algorithm, Budget, DoNotSortOrders, EvolveOrders, NearestTrainOrderCollectionAlgorithm,
+        NearestTrainOrderDistributionAlgorithm, NearestTrainSingleOrderAlgorithm, OrderSorter,
+        Solver, SortOrdersByNameAsc, SortOrdersRandomly, UNREACHABLE,
+    };
+    use crate::Input;
+
+    #[test]
+    fn test_solve_in_parallel() {
+        let input =
+            Input::try_from(include_str!("../data/generated.large.2.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let algorithms: &[&dyn Algorithm] = &[
+            &NearestTrainOrderCollectionAlgorithm,
+            &NearestTrainOrderDistributionAlgorithm,
+            &NearestTrainSingleOrderAlgorithm,
+        ];
+        let sorters: &[&dyn OrderSorter] =
+            &[&DoNotSortOrders, &SortOrdersByNameAsc, &SortOrdersRandomly];
+        let evolution = EvolveOrders {
+            generations: 2,
+            ..Default::default()
+        };
+
+        let results = |workers, seed| {
+            Solver::new(algorithms, sorters, distance)
+                .with_evolution(&evolution)
+                .with_workers(workers)
+                .with_seed(seed)
+                .solve(input.orders().to_vec(), input.trains().to_vec())
+                .expect("Results")
+                .into_iter()
+                .map(|r| {
+                    let algorithm = format!("{:?}", r.algorithm);
+                    let order_sorter = format!("{:?}", r.order_sorter);
+                    (algorithm, order_sorter, r.solution)
+                })
+                .collect_vec()
+        };
+
+        // The same seed gives the same results in the same order on any number of threads.
+        let sequential = results(1, 42);
+        assert_eq!(sequential.len(), 2 + 1 + 1 + 100 + 1);
+        assert_eq!(results(4, 42), sequential);
+        assert_ne!(results(4, 43), sequential);
+    }
+
+    #[test]
+    fn test_solve_within_budget() {
+        let input =
+            Input::try_from(include_str!("../data/generated.large.2.txt")).expect("Test input");
+        let network = Network::from(&input);
+        let distance = |from: &_, to: &_| network.distance(from, to).unwrap_or(UNREACHABLE);
+
+        let algorithms: &[&dyn Algorithm] = &[
+            &NearestTrainOrderCollectionAlgorithm,
+            &NearestTrainOrderDistributionAlgorithm,
+            &NearestTrainSingleOrderAlgorithm,
+        ];
+        let sorters: &[&dyn OrderSorter] =
+            &[&DoNotSortOrders, &SortOrdersByNameAsc, &SortOrdersRandomly];
+
+        let results = |budget| {
+            Solver::new(algorithms, sorters, distance)
+                .with_workers(2)
+                .with_random_iterations(10)
+                .with_budget(budget)
+                .solve(input.orders().to_vec(), input.trains().to_vec())
+                .expect("Results")
+                .len()
+        };
+
+        assert_eq!(results(Budget::default()), 2 + 2 + 10);
+
+        // Once cancelled, only algorithms with stable sorters, or none, run.
+        let cancelled = Budget::default();
+        cancelled.cancel();
+        assert_eq!(results(cancelled), 2 + 2);
+
+        // Random sorters run until the deadline, and every worker keeps its best result.
+        let started = Instant::now();
+        let sut = results(Budget::time(Duration::from_millis(300)));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!((2 + 2 + 1..=2 + 2 + 2).contains(&sut));
+    }
+}
@@ -0,0 +1,221 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use itertools::Itertools;
+
+use crate::timeline::Timeline;
+use crate::Solution;
+
+pub trait Objective: Debug + Sync {
+    /// Cost of a solution with `timeline`, lower is better.
+    fn cost(&self, timeline: &Timeline) -> f64;
+}
+
+/// Measures of a solution that objectives are built of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Measure {
+    /// Time when the last train finishes.
+    Makespan,
+    /// Sum of times trains work until they finish.
+    TrainTime,
+    /// Sum of times orders are delivered.
+    DeliveryTime,
+    /// Average time from when an order is ready for pickup until it is delivered.
+    LeadTime,
+}
+
+impl Objective for Measure {
+    fn cost(&self, timeline: &Timeline) -> f64 {
+        match self {
+            Measure::Makespan => timeline.makespan() as f64,
+            Measure::TrainTime => timeline.train_time() as f64,
+            Measure::DeliveryTime => timeline.delivery_time() as f64,
+            Measure::LeadTime => timeline.lead_time(),
+        }
+    }
+}
+
+/// Weighted sum of measures, parsed from `makespan` or `makespan=1,delivery-time=0.5`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedObjective(Vec<(Measure, f64)>);
+
+impl WeightedObjective {
+    pub fn new(terms: Vec<(Measure, f64)>) -> Self {
+        Self(terms)
+    }
+}
+
+impl Default for WeightedObjective {
+    fn default() -> Self {
+        Self(vec![(Measure::Makespan, 1.0)])
+    }
+}
+
+impl Objective for WeightedObjective {
+    fn cost(&self, timeline: &Timeline) -> f64 {
+        self.0
+            .iter()
+            .map(|(measure, weight)| weight * measure.cost(timeline))
+            .sum()
+    }
+}
+
+impl FromStr for WeightedObjective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|term| {
+                let (name, weight) = term.split_once('=').unwrap_or((term, "1"));
+
+                let measure = Measure::from_str(name.trim(), true).map_err(|_| {
+                    format!(
+                        "Unknown measure `{}`, expected one of: {}",
+                        name.trim(),
+                        Measure::value_variants()
+                            .iter()
+                            .filter_map(|m| m.to_possible_value())
+                            .map(|v| v.get_name())
+                            .join(", ")
+                    )
+                })?;
+
+                let weight = weight
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|e| format!("Invalid weight `{}`: {e}", weight.trim()))?;
+
+                if !weight.is_finite() || weight < 0.0 {
+                    return Err(format!("Invalid weight `{weight}`: must not be negative"));
+                }
+
+                Ok((measure, weight))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Display for WeightedObjective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0
+                .iter()
+                .filter_map(|(measure, weight)| Some((measure.to_possible_value()?, weight)))
+                .map(|(measure, weight)| format!("{}={weight}", measure.get_name()))
+                .join(",")
+        )
+    }
+}
+
+/// Position of a solution among others:
+/// failed runs go last, then solutions that miss more delivery deadlines, then costlier ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rank {
+    failed: bool,
+    late: usize,
+    cost: f64,
+}
+
+impl Rank {
+    pub fn new(objective: &dyn Objective, solution: &Solution, timeline: &Timeline) -> Self {
+        Self {
+            failed: solution.total_time() == u32::MAX,
+            late: solution.late().len(),
+            cost: objective.cost(timeline),
+        }
+    }
+}
+
+impl Eq for Rank {}
+
+impl PartialOrd for Rank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.failed, self.late)
+            .cmp(&(other.failed, other.late))
+            .then(self.cost.total_cmp(&other.cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::Station;
+    use crate::solver::objective::{Measure, Objective, WeightedObjective};
+    use crate::timeline::Timeline;
+    use crate::{Input, Move};
+
+    #[test]
+    fn test_parse_weighted_objective() {
+        assert_eq!(
+            "makespan".parse::<WeightedObjective>(),
+            Ok(WeightedObjective::default())
+        );
+        assert_eq!(
+            "delivery-time=0.5, train-time".parse::<WeightedObjective>(),
+            Ok(WeightedObjective::new(vec![
+                (Measure::DeliveryTime, 0.5),
+                (Measure::TrainTime, 1.0)
+            ]))
+        );
+        assert_eq!(
+            "speed".parse::<WeightedObjective>(),
+            Err("Unknown measure `speed`, expected one of: makespan, train-time, delivery-time, lead-time".to_owned())
+        );
+        assert_eq!(
+            "makespan=-1".parse::<WeightedObjective>(),
+            Err("Invalid weight `-1`: must not be negative".to_owned())
+        );
+        assert_eq!(
+            WeightedObjective::new(vec![(Measure::LeadTime, 2.0), (Measure::Makespan, 0.5)])
+                .to_string(),
+            "lead-time=2,makespan=0.5"
+        );
+    }
+
+    #[test]
+    fn test_measure_timeline() {
+        let input = Input::try_from(include_str!("../data/time_windows.txt")).expect("Test input");
+        let distance = |from: &Station, to: &Station| if from == to { 0 } else { 10 };
+        let order = |time, train: &str, from: &str, order: &str, to: &str| {
+            let load = vec![order.to_owned()];
+            Move::new(
+                time,
+                train.to_owned(),
+                from.to_owned(),
+                load.clone(),
+                to.to_owned(),
+                load,
+            )
+        };
+        let moves = vec![
+            order(0, "Q1", "A", "K1", "C"),
+            order(50, "Q1", "C", "K4", "A"),
+            order(0, "Q2", "D", "K3", "B"),
+            order(30, "Q2", "B", "K2", "D"),
+        ];
+
+        let sut = Timeline::new(input.orders(), input.trains(), &distance, &moves);
+
+        assert_eq!(sut.delivered_at("K2"), Some(40));
+        assert_eq!(Measure::Makespan.cost(&sut), 60.0);
+        assert_eq!(Measure::TrainTime.cost(&sut), 100.0);
+        assert_eq!(Measure::DeliveryTime.cost(&sut), 120.0);
+        // Every order is delivered 10 after it is ready.
+        assert_eq!(Measure::LeadTime.cost(&sut), 10.0);
+        assert_eq!(
+            WeightedObjective::new(vec![(Measure::Makespan, 1.0), (Measure::LeadTime, 2.0)])
+                .cost(&sut),
+            80.0
+        );
+    }
+}
@@ -1,12 +1,14 @@
 use std::fmt::Debug;
 
+use clap::ValueEnum;
 use rand::seq::SliceRandom;
+use rand::RngCore;
 
 use crate::model::{Order, Station};
 
-pub trait OrderSorter: std::fmt::Debug {
-    /// Sorts orders in certain order.
-    fn sort(&self, orders: &[Order]) -> Vec<Order>;
+pub trait OrderSorter: std::fmt::Debug + Sync {
+    /// Sorts orders in certain order, the ones that are not stable shuffle them with `rng`.
+    fn sort(&self, orders: &[Order], rng: &mut dyn RngCore) -> Vec<Order>;
 
     /// Whether it always sorts in the same ordera and `sort` call is idempotent.
     fn stable(&self) -> bool {
@@ -14,10 +16,65 @@ pub trait OrderSorter: std::fmt::Debug {
     }
 }
 
+/// Sorters that can be chosen for sort-sensitive algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SorterKind {
+    /// Keeps orders as they are in the input.
+    Unsorted,
+    /// Shortest distance from the location to the destination first.
+    DistanceAsc,
+    /// Longest distance from the location to the destination first.
+    DistanceDesc,
+    /// By name.
+    NameAsc,
+    /// By name, in reverse.
+    NameDesc,
+    /// Lightest first.
+    WeightAsc,
+    /// Heaviest first.
+    WeightDesc,
+    /// Earliest deadline first.
+    LatestDelivery,
+    /// Shuffles orders, every run anew.
+    Random,
+}
+
+impl SorterKind {
+    /// Sorters that run unless others are chosen.
+    pub const DEFAULT: [SorterKind; 9] = [
+        SorterKind::Unsorted,
+        SorterKind::DistanceAsc,
+        SorterKind::DistanceDesc,
+        SorterKind::NameAsc,
+        SorterKind::NameDesc,
+        SorterKind::WeightAsc,
+        SorterKind::WeightDesc,
+        SorterKind::LatestDelivery,
+        SorterKind::Random,
+    ];
+
+    pub fn sorter<'d>(
+        &self,
+        distance: &'d (dyn Fn(&Station, &Station) -> u32 + Sync),
+    ) -> Box<dyn OrderSorter + 'd> {
+        match self {
+            SorterKind::Unsorted => Box::new(DoNotSortOrders),
+            SorterKind::DistanceAsc => Box::new(SortOrdersByDistanceAsc::new(distance)),
+            SorterKind::DistanceDesc => Box::new(SortOrdersByDistanceDesc::new(distance)),
+            SorterKind::NameAsc => Box::new(SortOrdersByNameAsc),
+            SorterKind::NameDesc => Box::new(SortOrdersByNameDesc),
+            SorterKind::WeightAsc => Box::new(SortOrdersByWeightAsc),
+            SorterKind::WeightDesc => Box::new(SortOrdersByWeightDesc),
+            SorterKind::LatestDelivery => Box::new(SortOrdersByLatestDelivery),
+            SorterKind::Random => Box::new(SortOrdersRandomly),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SortOrdersByWeightAsc;
 impl OrderSorter for SortOrdersByWeightAsc {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
         v.sort_by_key(|o| o.weight());
         v
@@ -26,7 +83,7 @@ impl OrderSorter for SortOrdersByWeightAsc {
 #[derive(Debug)]
 pub struct SortOrdersByWeightDesc;
 impl OrderSorter for SortOrdersByWeightDesc {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
         v.sort_by_key(|o| o.weight());
         v.reverse();
@@ -37,7 +94,7 @@ impl OrderSorter for SortOrdersByWeightDesc {
 #[derive(Debug)]
 pub struct SortOrdersByNameAsc;
 impl OrderSorter for SortOrdersByNameAsc {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
         v.sort_by_key(|o| o.name().to_owned());
         v
@@ -47,7 +104,7 @@ impl OrderSorter for SortOrdersByNameAsc {
 #[derive(Debug)]
 pub struct SortOrdersByNameDesc;
 impl OrderSorter for SortOrdersByNameDesc {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
         v.sort_by_key(|o| o.name().to_owned());
         v.reverse();
@@ -55,6 +112,17 @@ impl OrderSorter for SortOrdersByNameDesc {
     }
 }
 
+/// Earliest deadline first, orders without a deadline go last.
+#[derive(Debug)]
+pub struct SortOrdersByLatestDelivery;
+impl OrderSorter for SortOrdersByLatestDelivery {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
+        let mut v = orders.to_vec();
+        v.sort_by_key(|o| o.latest_delivery().unwrap_or(u32::MAX));
+        v
+    }
+}
+
 pub struct SortOrdersByDistanceAsc<F> {
     distance: F,
 }
@@ -73,9 +141,9 @@ impl<F> Debug for SortOrdersByDistanceAsc<F> {
 
 impl<F> OrderSorter for SortOrdersByDistanceAsc<F>
 where
-    F: Fn(&Station, &Station) -> u32,
+    F: Fn(&Station, &Station) -> u32 + Sync,
 {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
         v.sort_by_key(|o| (self.distance)(&o.location(), &o.destination()));
         v
@@ -100,9 +168,9 @@ impl<F> Debug for SortOrdersByDistanceDesc<F> {
 
 impl<F> OrderSorter for SortOrdersByDistanceDesc<F>
 where
-    F: Fn(&Station, &Station) -> u32,
+    F: Fn(&Station, &Station) -> u32 + Sync,
 {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
         v.sort_by_key(|o| (self.distance)(&o.location(), &o.destination()));
         v.reverse();
@@ -112,7 +180,7 @@ where
 #[derive(Debug)]
 pub struct DoNotSortOrders;
 impl OrderSorter for DoNotSortOrders {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], _rng: &mut dyn RngCore) -> Vec<Order> {
         orders.to_vec()
     }
 }
@@ -120,9 +188,9 @@ impl OrderSorter for DoNotSortOrders {
 #[derive(Debug)]
 pub struct SortOrdersRandomly;
 impl OrderSorter for SortOrdersRandomly {
-    fn sort(&self, orders: &[Order]) -> Vec<Order> {
+    fn sort(&self, orders: &[Order], rng: &mut dyn RngCore) -> Vec<Order> {
         let mut v = orders.to_vec();
-        v.shuffle(&mut rand::thread_rng());
+        v.shuffle(rng);
         v
     }
 
@@ -133,6 +201,9 @@ impl OrderSorter for SortOrdersRandomly {
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     use super::{OrderSorter, SortOrdersRandomly};
 
     #[test]
@@ -145,7 +216,13 @@ mod tests {
             ("e", 5, "f", "g").into(),
         ];
 
-        // It is highly unlikely that the same order will be returned twice.
-        assert!(orders != SortOrdersRandomly.sort(&orders).as_slice());
+        let sorted = SortOrdersRandomly.sort(&orders, &mut StdRng::seed_from_u64(1));
+        assert!(orders != sorted.as_slice());
+
+        // The same seed shuffles orders the same way.
+        assert_eq!(
+            SortOrdersRandomly.sort(&orders, &mut StdRng::seed_from_u64(1)),
+            sorted
+        );
     }
 }
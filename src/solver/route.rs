@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::model::{Order, Station, Train};
+use crate::output::Move;
+use crate::solver::UNREACHABLE;
+use crate::Solution;
+
+/// Follows routes of trains, order by order, the way a solution encodes them in moves.
+/// A train unloads orders and then loads new ones at every stop, and waits until they are ready.
+pub(crate) struct Router<'r> {
+    pub(crate) orders: &'r [Order],
+    pub(crate) trains: &'r [Train],
+    pub(crate) distance: &'r dyn Fn(&Station, &Station) -> u32,
+    /// Stations trains start and end at, and pick up and deliver orders at.
+    stations: Vec<Station>,
+    /// Distances between `stations`, row by row.
+    distances: Vec<u32>,
+    pickups: Vec<usize>,
+    deliveries: Vec<usize>,
+    starts: Vec<usize>,
+    ends: Vec<Option<usize>>,
+}
+
+/// What a train does with an order at a stop of its route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Visit {
+    Pickup(usize),
+    Deliver(usize),
+}
+
+impl Visit {
+    pub(crate) fn order(self) -> usize {
+        match self {
+            Visit::Pickup(order) | Visit::Deliver(order) => order,
+        }
+    }
+}
+
+pub(crate) type Route = Vec<Visit>;
+
+/// Times of a train that follows a route.
+pub(crate) struct Schedule {
+    pub(crate) delivered_at: Vec<(usize, u32)>,
+    /// Time the train arrives and unloads orders after its last move, `None` if it does not move.
+    pub(crate) finished_at: Option<u32>,
+}
+
+impl<'r> Router<'r> {
+    pub(crate) fn new(
+        orders: &'r [Order],
+        trains: &'r [Train],
+        distance: &'r dyn Fn(&Station, &Station) -> u32,
+    ) -> Self {
+        let mut indices: HashMap<Station, usize> = HashMap::new();
+        let mut index = |station: Station| {
+            let next = indices.len();
+            *indices.entry(station).or_insert(next)
+        };
+
+        let pickups = orders.iter().map(|o| index(o.location())).collect_vec();
+        let deliveries = orders.iter().map(|o| index(o.destination())).collect_vec();
+        let starts = trains
+            .iter()
+            .map(|t| index(t.location().clone()))
+            .collect_vec();
+        let ends = trains
+            .iter()
+            .map(|t| t.end_station().cloned().map(&mut index))
+            .collect_vec();
+
+        let stations = indices
+            .into_iter()
+            .sorted_by_key(|(_, index)| *index)
+            .map(|(station, _)| station)
+            .collect_vec();
+        let distances = stations
+            .iter()
+            .cartesian_product(&stations)
+            .map(|(from, to)| distance(from, to))
+            .collect_vec();
+
+        Self {
+            orders,
+            trains,
+            distance,
+            stations,
+            distances,
+            pickups,
+            deliveries,
+            starts,
+            ends,
+        }
+    }
+
+    fn between(&self, from: usize, to: usize) -> u32 {
+        self.distances[from * self.stations.len() + to]
+    }
+
+    /// Routes of trains in `solution`, `None` if it moves unknown trains or orders.
+    /// Orders are picked up where they are loaded first, and delivered where they are unloaded last.
+    pub(crate) fn routes(&self, solution: &Solution) -> Option<Vec<Route>> {
+        let orders: HashMap<&str, usize> = self
+            .orders
+            .iter()
+            .enumerate()
+            .map(|(index, order)| (order.name(), index))
+            .collect();
+
+        let mut routes = vec![Route::new(); self.trains.len()];
+
+        for (name, moves) in solution.moves().iter().into_group_map_by(|m| m.train()) {
+            let train = self.trains.iter().position(|t| t.name() == name)?;
+            let moves = moves.into_iter().sorted_by_key(|m| m.time()).collect_vec();
+
+            for (index, m) in moves.iter().enumerate() {
+                let previous = match index {
+                    0 => &[],
+                    _ => moves[index - 1].unload(),
+                };
+                let next = moves.get(index + 1).map(|m| m.load()).unwrap_or_default();
+
+                for order in m.load().iter().filter(|o| !previous.contains(o)) {
+                    routes[train].push(Visit::Pickup(*orders.get(order.as_str())?));
+                }
+                for order in m.unload().iter().filter(|o| !next.contains(o)) {
+                    routes[train].push(Visit::Deliver(*orders.get(order.as_str())?));
+                }
+            }
+        }
+
+        Some(routes)
+    }
+
+    /// Moves of trains that follow `routes`, `None` if a route is not feasible.
+    pub(crate) fn moves(&self, routes: &[Route]) -> Option<Vec<Move>> {
+        let mut moves = vec![];
+        for (train, route) in routes.iter().enumerate() {
+            self.simulate(train, route, Some(&mut moves))?;
+        }
+        Some(moves)
+    }
+
+    /// Times of a train that follows `route`, and its moves recorded to `moves` if given.
+    /// `None` if the route picks up an order twice, delivers one it does not carry,
+    /// leaves one on board, exceeds the train capacity, or goes to a station it can't reach.
+    /// Consecutive visits to a station make a single stop, where orders are unloaded first.
+    pub(crate) fn simulate(
+        &self,
+        index: usize,
+        route: &[Visit],
+        mut moves: Option<&mut Vec<Move>>,
+    ) -> Option<Schedule> {
+        let train = &self.trains[index];
+
+        let mut schedule = Schedule {
+            delivered_at: vec![],
+            finished_at: None,
+        };
+        let mut location = self.starts[index];
+        let mut departure = train.traveled_time();
+        let mut on_board: Vec<usize> = vec![];
+        let mut weight = 0;
+
+        let station_of = |visit: &&Visit| match **visit {
+            Visit::Pickup(order) => self.pickups[order],
+            Visit::Deliver(order) => self.deliveries[order],
+        };
+
+        for (station, stop) in &route.iter().group_by(station_of) {
+            let stop = stop.copied().collect_vec();
+            let mut arrival = departure;
+            let moved = station != location;
+
+            if moved {
+                let distance = self.between(location, station);
+                if distance == UNREACHABLE {
+                    return None;
+                }
+
+                if let Some(moves) = moves.as_mut() {
+                    let names = on_board
+                        .iter()
+                        .map(|&o| self.orders[o].name().to_owned())
+                        .collect_vec();
+                    moves.push(Move::new(
+                        departure,
+                        train.name().to_owned(),
+                        self.stations[location].name().to_owned(),
+                        names.clone(),
+                        self.stations[station].name().to_owned(),
+                        names,
+                    ));
+                }
+
+                arrival = departure + train.travel_time(distance);
+                location = station;
+            }
+
+            let mut unloading = 0;
+            let delivered = schedule.delivered_at.len();
+            for order in stop.iter().filter_map(|visit| match visit {
+                Visit::Deliver(order) => Some(*order),
+                Visit::Pickup(_) => None,
+            }) {
+                let position = on_board.iter().position(|&o| o == order)?;
+                on_board.remove(position);
+                weight -= self.orders[order].weight();
+                unloading += self.orders[order].handling_time();
+                schedule.delivered_at.push((order, 0));
+            }
+
+            // Orders are delivered once all of them are unloaded.
+            for (_, time) in &mut schedule.delivered_at[delivered..] {
+                *time = arrival + unloading;
+            }
+            if moved {
+                schedule.finished_at = Some(arrival + unloading);
+            }
+
+            let mut loading = 0;
+            let mut ready = 0;
+            for order in stop.iter().filter_map(|visit| match visit {
+                Visit::Pickup(order) => Some(*order),
+                Visit::Deliver(_) => None,
+            }) {
+                let details = &self.orders[order];
+                if on_board.contains(&order) {
+                    return None;
+                }
+                on_board.push(order);
+                weight += details.weight();
+                loading += details.handling_time();
+                ready = ready.max(details.earliest_pickup());
+            }
+
+            if weight > train.capacity() {
+                return None;
+            }
+
+            departure = (arrival + unloading).max(ready) + loading;
+        }
+
+        if !on_board.is_empty() {
+            return None;
+        }
+
+        if let Some(end_station) = self.ends[index] {
+            if location != end_station {
+                let distance = self.between(location, end_station);
+                if distance == UNREACHABLE {
+                    return None;
+                }
+                if let Some(moves) = moves {
+                    moves.push(Move::new(
+                        departure,
+                        train.name().to_owned(),
+                        self.stations[location].name().to_owned(),
+                        vec![],
+                        self.stations[end_station].name().to_owned(),
+                        vec![],
+                    ));
+                }
+                schedule.finished_at = Some(departure + train.travel_time(distance));
+            }
+        }
+
+        Some(schedule)
+    }
+}
@@ -1,15 +1,36 @@
-use std::iter::once;
-
+use anyhow::bail;
 use itertools::Itertools;
 
 use crate::model::{Order, Station, Train};
+use crate::output::Move;
+
+/// Distance between stations that are not connected.
+pub const UNREACHABLE: u32 = u32::MAX;
+
+/// Checks that some train can reach the order location, and the destination is reachable from there.
+pub fn is_serviceable(
+    distance: &dyn Fn(&Station, &Station) -> u32,
+    trains: &[Train],
+    order: &Order,
+) -> bool {
+    order.is_delivered()
+        || distance(&order.location(), &order.destination()) != UNREACHABLE
+            && trains
+                .iter()
+                .any(|train| distance(train.location(), &order.location()) != UNREACHABLE)
+}
 
 /// Looks up a train with the nearest arrival time to `location` with at least `min_capacity`.
-/// It can only fail if there is no train with required capacity at all.
+/// The arrival time accounts for the train speed and time spent handling earlier orders,
+/// so a faster or less busy train further away may win.
+/// A train that has to return to its end station is ranked by the arrival time plus the time to return
+/// from `destination`, where the assignment ends, so trains finishing near home are preferred.
+/// It fails if there is no train with required capacity that can reach `location`.
 pub fn find_nearest_train(
     distance: &dyn Fn(&Station, &Station) -> u32,
     trains: &[Train],
     location: &Station,
+    destination: &Station,
     min_capacity: u32,
 ) -> Option<(usize, u32)> {
     // Sort trains by arrival time that is traveled time + travel time to location.
@@ -18,45 +39,284 @@ pub fn find_nearest_train(
         .enumerate()
         // Only trains with enough capacity.
         .filter(|(_, train)| train.capacity() >= min_capacity)
+        // Only trains that can get to location.
+        .map(|(index, train)| (index, train, distance(train.location(), location)))
+        .filter(|(_, _, distance)| *distance != UNREACHABLE)
         // Calculate availability
-        .map(|(index, train)| {
+        .map(|(index, train, distance)| {
             (
                 index,
-                train.traveled_time() + distance(train.location(), location),
+                train,
+                train.traveled_time() + train.travel_time(distance),
             )
         })
-        .min_by_key(|(_, time)| *time)
+        .min_by_key(|(_, train, time)| {
+            time.saturating_add(return_time(distance, train, destination))
+        })
+        .map(|(index, _, time)| (index, time))
+}
+
+/// Time for `train` to return from `location` to its end station, 0 if it has none.
+fn return_time(
+    distance: &dyn Fn(&Station, &Station) -> u32,
+    train: &Train,
+    location: &Station,
+) -> u32 {
+    match train.end_station() {
+        Some(end_station) => match distance(location, end_station) {
+            UNREACHABLE => UNREACHABLE,
+            distance => train.travel_time(distance),
+        },
+        None => 0,
+    }
+}
+
+/// Moves every train that has an end station back to it, after the train finishes its work.
+/// It fails if a train can't reach its end station.
+pub fn return_trains(
+    distance: &dyn Fn(&Station, &Station) -> u32,
+    trains: &mut [Train],
+    moves: &mut Vec<Move>,
+) -> anyhow::Result<()> {
+    for train in trains.iter_mut() {
+        let Some(end_station) = train.end_station().cloned() else {
+            continue;
+        };
+
+        if train.location() == &end_station {
+            continue;
+        }
+
+        let return_distance = distance(train.location(), &end_station);
+        if return_distance == UNREACHABLE {
+            bail!(
+                "Train {} can't return to {end_station} from {}",
+                train.name(),
+                train.location()
+            );
+        }
+
+        moves.push(Move::new(
+            train.traveled_time(),
+            train.name().to_owned(),
+            train.location().name().to_owned(),
+            vec![],
+            end_station.name().to_owned(),
+            vec![],
+        ));
+
+        train.move_to(&end_station, return_distance);
+    }
+
+    Ok(())
 }
 
+/// Routes through at most this many stations are found exactly, longer ones heuristically.
+const EXACT_ROUTE_LIMIT: usize = 12;
+
+/// Finds the shortest route from `start` that visits all `destinations`.
 pub fn calculate_best_route_for_distribution(
     distance: &dyn Fn(&Station, &Station) -> u32,
     start: &Station,
     destinations: &[Station],
 ) -> (Vec<Station>, u32) {
-    Itertools::permutations(0..destinations.len(), destinations.len())
-        .map(|nodes| {
-            (
-                nodes.iter().map(|i| destinations[*i].clone()).collect_vec(),
-                once(start)
-                    .chain(nodes.iter().map(|i| &destinations[*i]))
-                    .tuples()
-                    .fold(0_u32, |acc, (a, b)| acc + distance(a, b)),
-            )
-        })
-        // .inspect(|(stations, distance)| println!("{stations:?}: {distance}"))
-        .min_by_key(|(_, d)| *d)
-        .unwrap()
+    best_route(&RouteDistances::new(
+        distance,
+        Some(start),
+        destinations,
+        None,
+    ))
+    .map_stations(destinations)
 }
 
+/// Finds the shortest route that visits all `stations` and ends at `destination`.
 pub fn calculate_best_route_for_collection(
     distance: &dyn Fn(&Station, &Station) -> u32,
     stations: &[Station],
     destination: &Station,
 ) -> (Vec<Station>, u32) {
-    let (mut route, distance) =
-        calculate_best_route_for_distribution(&distance, destination, stations);
-    route.reverse();
-    (route, distance)
+    best_route(&RouteDistances::new(
+        distance,
+        None,
+        stations,
+        Some(destination),
+    ))
+    .map_stations(stations)
+}
+
+/// Distances between stations a route goes through, and from its start and to its end if they are fixed.
+/// Distances can be asymmetric, so the direction matters.
+/// A route with an unreachable station is `UNREACHABLE` long.
+struct RouteDistances {
+    fixed_start: bool,
+    from_start: Vec<u32>,
+    to_end: Vec<u32>,
+    between: Vec<Vec<u32>>,
+}
+
+impl RouteDistances {
+    fn new(
+        distance: &dyn Fn(&Station, &Station) -> u32,
+        start: Option<&Station>,
+        stations: &[Station],
+        end: Option<&Station>,
+    ) -> Self {
+        Self {
+            fixed_start: start.is_some(),
+            from_start: stations
+                .iter()
+                .map(|s| start.map_or(0, |start| distance(start, s)))
+                .collect(),
+            to_end: stations
+                .iter()
+                .map(|s| end.map_or(0, |end| distance(s, end)))
+                .collect(),
+            between: stations
+                .iter()
+                .map(|a| stations.iter().map(|b| distance(a, b)).collect())
+                .collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.between.len()
+    }
+
+    /// Length of the route through stations in the order of `route`.
+    fn length(&self, route: &[usize]) -> u32 {
+        match (route.first(), route.last()) {
+            (Some(&first), Some(&last)) => route
+                .iter()
+                .tuple_windows()
+                .fold(self.from_start[first], |acc, (&a, &b)| {
+                    acc.saturating_add(self.between[a][b])
+                })
+                .saturating_add(self.to_end[last]),
+            _ => 0,
+        }
+    }
+}
+
+/// Order of stations in a route, with its length.
+struct BestRoute(Vec<usize>, u32);
+
+impl BestRoute {
+    fn map_stations(self, stations: &[Station]) -> (Vec<Station>, u32) {
+        let BestRoute(route, length) = self;
+        (
+            route.into_iter().map(|i| stations[i].clone()).collect(),
+            length,
+        )
+    }
+}
+
+/// The shortest route exactly for a few stations, and a short one for more.
+fn best_route(distances: &RouteDistances) -> BestRoute {
+    if distances.len() <= EXACT_ROUTE_LIMIT {
+        held_karp(distances)
+    } else {
+        two_opt(distances, nearest_neighbour(distances))
+    }
+}
+
+/// The shortest route by the Held–Karp dynamic programming over subsets of visited stations.
+/// Of routes equally short, it is the first one in the lexicographic order of stations.
+fn held_karp(distances: &RouteDistances) -> BestRoute {
+    let n = distances.len();
+    let full = (1_usize << n) - 1;
+
+    // The shortest way to visit the rest of stations and the end, having visited `mask` and stopped at `last`.
+    let mut remaining = vec![vec![UNREACHABLE; n]; full + 1];
+    remaining[full] = distances.to_end.clone();
+    for mask in (1..full).rev() {
+        for last in (0..n).filter(|last| mask & (1 << last) != 0) {
+            remaining[mask][last] = (0..n)
+                .filter(|next| mask & (1 << next) == 0)
+                .map(|next| {
+                    distances.between[last][next].saturating_add(remaining[mask | 1 << next][next])
+                })
+                .min()
+                .unwrap_or(UNREACHABLE);
+        }
+    }
+
+    let Some(length) = (0..n)
+        .map(|first| distances.from_start[first].saturating_add(remaining[1 << first][first]))
+        .min()
+    else {
+        return BestRoute(vec![], 0);
+    };
+
+    // Goes along the shortest route, taking the first station that keeps it shortest.
+    let mut route: Vec<usize> = vec![];
+    let mut mask = 0;
+    let mut left = length;
+    while mask != full {
+        let next = (0..n)
+            .filter(|next| mask & (1 << next) == 0)
+            .find(|&next| {
+                let step = match route.last() {
+                    Some(&last) => distances.between[last][next],
+                    None => distances.from_start[next],
+                };
+                step.saturating_add(remaining[mask | 1 << next][next]) == left
+            })
+            .expect("Station on the shortest route");
+        left = remaining[mask | 1 << next][next];
+        mask |= 1 << next;
+        route.push(next);
+    }
+
+    BestRoute(route, length)
+}
+
+/// A route that goes to the nearest station not visited yet, from the start if it is fixed,
+/// or backwards from the end otherwise.
+fn nearest_neighbour(distances: &RouteDistances) -> Vec<usize> {
+    let backwards = !distances.fixed_start;
+    let mut left = (0..distances.len()).collect_vec();
+    let mut route: Vec<usize> = vec![];
+
+    while !left.is_empty() {
+        let step = |next: usize| match (route.last(), backwards) {
+            (Some(&last), false) => distances.between[last][next],
+            (Some(&last), true) => distances.between[next][last],
+            (None, false) => distances.from_start[next],
+            (None, true) => distances.to_end[next],
+        };
+        let nearest = left
+            .iter()
+            .position_min_by_key(|&&next| step(next))
+            .unwrap();
+        route.push(left.remove(nearest));
+    }
+
+    if backwards {
+        route.reverse();
+    }
+    route
+}
+
+/// Improves `route` by reversing its parts while that makes it shorter.
+fn two_opt(distances: &RouteDistances, mut route: Vec<usize>) -> BestRoute {
+    let mut length = distances.length(&route);
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for (start, end) in (0..route.len()).tuple_combinations() {
+            route[start..=end].reverse();
+            let reversed = distances.length(&route);
+            if reversed < length {
+                length = reversed;
+                improved = true;
+            } else {
+                route[start..=end].reverse();
+            }
+        }
+    }
+
+    BestRoute(route, length)
 }
 
 pub fn group_orders_by_location(orders: &[Order]) -> Vec<(Station, u32, Vec<&Order>)> {
@@ -99,6 +359,7 @@ mod tests {
     use crate::network::Network;
     use crate::solver::utils::{
         calculate_best_route_for_collection, calculate_best_route_for_distribution,
+        EXACT_ROUTE_LIMIT,
     };
     use crate::Input;
 
@@ -108,7 +369,7 @@ mod tests {
             .expect("Test input");
 
         let network = Network::from(&input);
-        let distance: &dyn Fn(&Station, &Station) -> u32 = &|a, b| network.distance(a, b);
+        let distance: &dyn Fn(&Station, &Station) -> u32 = &|a, b| network.distance(a, b).unwrap();
 
         let orders = input
             .orders()
@@ -129,12 +390,12 @@ mod tests {
             })
             .collect_vec();
 
-        assert_eq!(result[0], ("N0".into(), vec!["N3".into(), "N1".into()], 6));
-        assert_eq!(result[1], ("N1".into(), vec!["N4".into(), "N2".into()], 5));
-        assert_eq!(result[2], ("N2".into(), vec!["N0".into(), "N1".into()], 3));
+        assert_eq!(result[0], ("N0".into(), vec!["N3".into(), "N1".into()], 9));
+        assert_eq!(result[1], ("N1".into(), vec!["N4".into(), "N2".into()], 6));
+        assert_eq!(result[2], ("N2".into(), vec!["N0".into(), "N1".into()], 12));
         assert_eq!(
             result[3],
-            ("N4".into(), vec!["N4".into(), "N3".into(), "N0".into()], 6)
+            ("N4".into(), vec!["N4".into(), "N3".into(), "N0".into()], 8)
         );
     }
 
@@ -144,7 +405,7 @@ mod tests {
             .expect("Test input");
 
         let network = Network::from(&input);
-        let distance: &dyn Fn(&Station, &Station) -> u32 = &|a, b| network.distance(a, b);
+        let distance: &dyn Fn(&Station, &Station) -> u32 = &|a, b| network.distance(a, b).unwrap();
 
         let orders = input
             .orders()
@@ -165,12 +426,95 @@ mod tests {
             })
             .collect_vec();
 
-        assert_eq!(result[0], ("N0".into(), vec!["N4".into(), "N2".into()], 3));
-        assert_eq!(result[1], ("N1".into(), vec!["N0".into(), "N2".into()], 6));
-        assert_eq!(result[2], ("N3".into(), vec!["N0".into(), "N4".into()], 2));
+        assert_eq!(result[0], ("N0".into(), vec!["N4".into(), "N2".into()], 4));
+        assert_eq!(result[1], ("N1".into(), vec!["N0".into(), "N2".into()], 9));
+        assert_eq!(result[2], ("N3".into(), vec!["N0".into(), "N4".into()], 6));
         assert_eq!(
             result[3],
-            ("N4".into(), vec!["N1".into(), "N3".into(), "N4".into()], 3)
+            ("N4".into(), vec!["N1".into(), "N3".into(), "N4".into()], 5)
+        );
+    }
+
+    #[test]
+    fn test_calculate_best_route_for_many_stations() {
+        let input = Input::try_from(include_str!("../data/generated.extralarge.1.txt"))
+            .expect("Test input");
+
+        let network = Network::from(&input);
+        let distance: &dyn Fn(&Station, &Station) -> u32 = &|a, b| network.distance(a, b).unwrap();
+        let length = |route: &[Station]| {
+            route
+                .iter()
+                .tuple_windows()
+                .map(|(a, b)| distance(a, b))
+                .sum::<u32>()
+        };
+
+        let start = input.stations()[0].clone();
+        let stations = input.stations()[1..=40].to_vec();
+
+        // A few stations are routed exactly, as every permutation would.
+        let few = &stations[..8];
+        let shortest = few
+            .iter()
+            .cloned()
+            .permutations(few.len())
+            .map(|route| length(&[vec![start.clone()], route].concat()))
+            .min();
+        let (route, route_length) = calculate_best_route_for_distribution(&distance, &start, few);
+        assert_eq!(Some(route_length), shortest);
+        assert_eq!(
+            route.iter().sorted().collect_vec(),
+            few.iter().sorted().collect_vec()
+        );
+
+        // Many stations are routed heuristically, without trying every permutation.
+        assert!(stations.len() > EXACT_ROUTE_LIMIT);
+        let (route, distance) = calculate_best_route_for_collection(&distance, &stations, &start);
+        assert_eq!(
+            distance,
+            length(&[route.clone(), vec![start.clone()]].concat())
+        );
+        assert_eq!(
+            route.iter().sorted().collect_vec(),
+            stations.iter().sorted().collect_vec()
+        );
+    }
+
+    #[test]
+    fn test_calculate_best_route_for_one_way_edges() {
+        let input = Input::try_from(indoc::indoc! {"
+            3
+            A
+            B
+            C
+
+            3
+            E1,A,B,10,->
+            E2,B,C,20
+            E3,C,A,5,->
+
+            0
+
+            1
+            Q1,1,A
+        "})
+        .expect("Test input");
+
+        let network = Network::from(&input);
+        let distance: &dyn Fn(&Station, &Station) -> u32 = &|a, b| network.distance(a, b).unwrap();
+
+        assert_eq!(
+            calculate_best_route_for_collection(&distance, &["C".into(), "B".into()], &"A".into()),
+            (vec!["B".into(), "C".into()], 25)
+        );
+        assert_eq!(
+            calculate_best_route_for_distribution(
+                &distance,
+                &"A".into(),
+                &["C".into(), "B".into()]
+            ),
+            (vec!["B".into(), "C".into()], 30)
         );
     }
 }
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::model::{Order, Station, Train};
+use crate::output::Move;
+use crate::solver::UNREACHABLE;
+
+/// Times when trains finish their work and orders reach their destinations, replayed from moves.
+#[derive(Debug, Clone)]
+pub struct Timeline<'t> {
+    orders: HashMap<&'t str, &'t Order>,
+    /// Time an order is unloaded at the end of its trip.
+    delivered_at: HashMap<&'t str, u32>,
+    /// Time a train arrives and unloads orders after its last move.
+    finished_at: HashMap<&'t str, u32>,
+}
+
+impl<'t> Timeline<'t> {
+    /// Replays `moves` of `trains`, orders are unloaded at every stop for their handling time.
+    /// Moves of unknown trains and moves between stations that are not connected are skipped.
+    pub fn new(
+        orders: &'t [Order],
+        trains: &'t [Train],
+        distance: &dyn Fn(&Station, &Station) -> u32,
+        moves: &'t [Move],
+    ) -> Self {
+        let orders = orders
+            .iter()
+            .map(|o| (o.name(), o))
+            .collect::<HashMap<_, _>>();
+
+        let mut delivered_at = HashMap::new();
+        let mut finished_at = HashMap::new();
+
+        for (name, moves) in moves.iter().into_group_map_by(|m| m.train()) {
+            let Some(train) = trains.iter().find(|t| t.name() == name) else {
+                continue;
+            };
+
+            let moves = moves.into_iter().sorted_by_key(|m| m.time()).collect_vec();
+
+            for (index, m) in moves.iter().enumerate() {
+                let distance = distance(&m.from().into(), &m.to().into());
+                if distance == UNREACHABLE {
+                    continue;
+                }
+
+                // Orders that are not loaded for the next move leave the train.
+                let next_load = moves.get(index + 1).map(|m| m.load()).unwrap_or_default();
+                let unloaded = m
+                    .unload()
+                    .iter()
+                    .filter(|o| !next_load.contains(o))
+                    .map(String::as_str)
+                    .collect_vec();
+
+                let unloaded_at = m.time()
+                    + train.travel_time(distance)
+                    + unloaded
+                        .iter()
+                        .filter_map(|o| orders.get(o))
+                        .map(|o| o.handling_time())
+                        .sum::<u32>();
+
+                for order in unloaded {
+                    delivered_at.insert(order, unloaded_at);
+                }
+
+                let finished = finished_at.entry(name).or_default();
+                *finished = unloaded_at.max(*finished);
+            }
+        }
+
+        Self {
+            orders,
+            delivered_at,
+            finished_at,
+        }
+    }
+
+    /// Time when the last train arrives and unloads its orders.
+    pub fn makespan(&self) -> u32 {
+        self.finished_at.values().max().copied().unwrap_or_default()
+    }
+
+    /// Sum of times trains spend from the start until they finish.
+    pub fn train_time(&self) -> u64 {
+        self.finished_at.values().map(|&time| time as u64).sum()
+    }
+
+    /// Sum of times orders are delivered.
+    pub fn delivery_time(&self) -> u64 {
+        self.delivered_at.values().map(|&time| time as u64).sum()
+    }
+
+    /// Average time from when an order is ready for pickup until it is delivered, 0 if none is.
+    pub fn lead_time(&self) -> f64 {
+        let lead_times = self
+            .delivered_at
+            .iter()
+            .filter_map(|(name, time)| {
+                let order = self.orders.get(name)?;
+                Some(time.saturating_sub(order.earliest_pickup()) as f64)
+            })
+            .collect_vec();
+
+        if lead_times.is_empty() {
+            0.0
+        } else {
+            lead_times.iter().sum::<f64>() / lead_times.len() as f64
+        }
+    }
+
+    /// Time when `order` is delivered, `None` if it is not.
+    pub fn delivered_at(&self, order: &str) -> Option<u32> {
+        self.delivered_at.get(order).copied()
+    }
+
+    pub(crate) fn set_delivered_at(&mut self, order: &'t str, time: u32) {
+        self.delivered_at.insert(order, time);
+    }
+
+    /// Sets when `train` finishes, `None` if it does not move.
+    pub(crate) fn set_finished_at(&mut self, train: &'t str, time: Option<u32>) {
+        match time {
+            Some(time) => self.finished_at.insert(train, time),
+            None => self.finished_at.remove(train),
+        };
+    }
+}
@@ -0,0 +1,824 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use itertools::Itertools;
+
+use crate::model::{Edge, Order, Station, Train};
+use crate::network::{DistanceBackend, Network};
+use crate::output::Move;
+use crate::scheduler::{legs, Interval};
+use crate::{Input, Solution};
+
+/// Replays moves of `solution` in time order against `input` and checks that:
+/// - every train departs from the station it is at, after it has handled orders there,
+///   and its moves do not overlap in time;
+/// - the weight on board never exceeds the train capacity;
+/// - every order is picked up at its location and dropped at its destination by a single train;
+/// - no order is picked up before its earliest pickup, and orders reported as late are exactly
+///   those delivered after their latest delivery;
+/// - no edge is used by more trains at once than its capacity,
+///   and no station holds more trains than its capacity;
+/// - every train with an end station finishes there;
+/// - the total time of the solution is the time the last train arrives and unloads its orders;
+/// - orders reported as unserviceable can't be reached by any train, and only they are not delivered.
+///
+/// A move lists orders on board when the train leaves `from` (P1) and arrives to `to` (P2),
+/// so an order is loaded where it appears first time and unloaded where it appears last time.
+/// At every stop orders are unloaded first, and then loaded, each one for its handling time.
+pub fn verify(input: &Input, solution: &Solution) -> anyhow::Result<()> {
+    let violations = Verifier::new(input).replay(solution);
+
+    if !violations.is_empty() {
+        bail!(
+            "Solution is invalid:\n{}",
+            violations.iter().map(|v| format!("  - {v}")).join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+struct Verifier<'v> {
+    network: Network<'v>,
+    stations: HashSet<&'v Station>,
+    edges: &'v [Edge],
+    input: &'v Input,
+    orders: HashMap<&'v str, &'v Order>,
+    trains: HashMap<&'v str, &'v Train>,
+}
+
+/// State of a train during the replay.
+struct TrainState<'s> {
+    location: Station,
+    available_at: u32,
+    on_board: Vec<&'s str>,
+}
+
+impl<'v> Verifier<'v> {
+    fn new(input: &'v Input) -> Self {
+        Self {
+            network: Network::new(input, DistanceBackend::Dijkstra),
+            stations: input.stations().iter().collect(),
+            edges: input.edges(),
+            input,
+            orders: input.orders().iter().map(|o| (o.name(), o)).collect(),
+            trains: input.trains().iter().map(|t| (t.name(), t)).collect(),
+        }
+    }
+
+    fn replay(&self, solution: &Solution) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        // Train that picked up an order, and the station and time the order left the train.
+        let mut picked_up: HashMap<&str, &str> = HashMap::new();
+        let mut dropped_at: HashMap<&str, (Station, u32)> = HashMap::new();
+
+        // Times trains are on edges with limited capacity.
+        let mut edge_usage: HashMap<&str, Vec<Interval>> = HashMap::new();
+        // Times trains stay at stations with limited capacity.
+        let mut station_usage: HashMap<Station, Vec<Interval>> = HashMap::new();
+
+        let mut makespan = 0;
+        // Stations where trains stay after their last move.
+        let mut last_locations: HashMap<&str, Station> = HashMap::new();
+
+        let moves_by_train = solution.moves().iter().into_group_map_by(|m| m.train());
+
+        for (name, moves) in moves_by_train.into_iter().sorted_by_key(|(name, _)| *name) {
+            let Some(train) = self.trains.get(name) else {
+                violations.push(format!("Unknown train `{name}`"));
+                continue;
+            };
+
+            let mut state = TrainState {
+                location: train.location().clone(),
+                available_at: 0,
+                on_board: vec![],
+            };
+
+            for m in moves.into_iter().sorted_by_key(|m| m.time()) {
+                if let Err(violation) = self.check_stations(m) {
+                    violations.push(violation);
+                    break;
+                }
+
+                let from = Station::from(m.from());
+
+                if from != state.location {
+                    violations.push(format!(
+                        "Train {name} departs from {from} at {}, but it is at {}",
+                        m.time(),
+                        state.location
+                    ));
+                }
+
+                // Orders that are not on board anymore have been unloaded at the departure station,
+                // and then new orders have been loaded.
+                let unloaded = state
+                    .on_board
+                    .iter()
+                    .copied()
+                    .filter(|o| !m.load().iter().any(|l| l == o))
+                    .collect_vec();
+                let loaded = m
+                    .load()
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|o| !state.on_board.contains(o))
+                    .collect_vec();
+
+                let unloaded_at = state.available_at + self.handling_time(&unloaded);
+                let loading_time = self.handling_time(&loaded);
+
+                if m.time() < state.available_at {
+                    violations.push(format!(
+                        "Train {name} departs at {}, but it arrives to {} at {}",
+                        m.time(),
+                        state.location,
+                        state.available_at
+                    ));
+                } else if m.time() < unloaded_at + loading_time {
+                    violations.push(format!(
+                        "Train {name} departs at {}, but it handles orders at {} until {}",
+                        m.time(),
+                        state.location,
+                        unloaded_at + loading_time
+                    ));
+                }
+
+                let Some(arrival) = self.arrival(train, m) else {
+                    violations.push(format!(
+                        "Train {name} can't reach {} from {} at {}",
+                        m.to(),
+                        m.from(),
+                        m.time()
+                    ));
+                    break;
+                };
+
+                if m.load().iter().sorted().ne(m.unload().iter().sorted()) {
+                    violations.push(format!(
+                        "Train {name} changes orders on board between {} and {} at {}",
+                        m.from(),
+                        m.to(),
+                        m.time()
+                    ));
+                }
+
+                for order in unloaded {
+                    dropped_at.insert(order, (from.clone(), unloaded_at));
+                }
+
+                // Loading finishes at the departure, and can't start before the earliest pickup.
+                let loading_start = m.time().saturating_sub(loading_time);
+
+                for order in loaded {
+                    let Some(&details) = self.orders.get(order) else {
+                        violations.push(format!("Unknown order `{order}`"));
+                        continue;
+                    };
+
+                    if let Some(other) = picked_up.insert(details.name(), name) {
+                        violations.push(format!(
+                            "Order {order} is picked up by {name} at {} after it was picked up by {other}",
+                            m.time()
+                        ));
+                    } else if details.location() != from {
+                        violations.push(format!(
+                            "Order {order} is picked up at {from}, but it is at {}",
+                            details.location()
+                        ));
+                    } else if loading_start < details.earliest_pickup() {
+                        violations.push(format!(
+                            "Order {order} is picked up at {loading_start}, before its earliest pickup {}",
+                            details.earliest_pickup()
+                        ));
+                    }
+                }
+
+                let weight = m
+                    .load()
+                    .iter()
+                    .filter_map(|o| self.orders.get(o.as_str()))
+                    .map(|o| o.weight())
+                    .sum::<u32>();
+
+                if weight > train.capacity() {
+                    violations.push(format!(
+                        "Train {name} departs from {from} at {} with {weight} on board, but its capacity is {}",
+                        m.time(),
+                        train.capacity()
+                    ));
+                }
+
+                for (edge, (start, end)) in legs(&self.network, train, m).unwrap_or_default() {
+                    if edge.capacity().is_some() {
+                        edge_usage
+                            .entry(edge.name())
+                            .or_default()
+                            .push((m.time() + start, m.time() + end));
+                    }
+                }
+
+                if self.input.station_capacity(&from).is_some() {
+                    station_usage
+                        .entry(from.clone())
+                        .or_default()
+                        .push((state.available_at, m.time()));
+                }
+
+                state.on_board = m.load().iter().map(String::as_str).collect();
+                state.available_at = arrival;
+                state.location = m.to().into();
+            }
+
+            // The train stays at the last station.
+            if self.input.station_capacity(&state.location).is_some() {
+                station_usage
+                    .entry(state.location.clone())
+                    .or_default()
+                    .push((state.available_at, u32::MAX));
+            }
+
+            // Everything left on board is unloaded at the last station.
+            let unloaded_at = state.available_at + self.handling_time(&state.on_board);
+            for order in state.on_board {
+                dropped_at.insert(order, (state.location.clone(), unloaded_at));
+            }
+
+            makespan = makespan.max(unloaded_at);
+            last_locations.insert(name, state.location);
+        }
+
+        // Trains without moves stay at their initial stations all the time.
+        for train in self.trains.values() {
+            if !last_locations.contains_key(train.name())
+                && self.input.station_capacity(train.location()).is_some()
+            {
+                station_usage
+                    .entry(train.location().clone())
+                    .or_default()
+                    .push((0, u32::MAX));
+            }
+        }
+
+        for train in self.trains.values().sorted_by_key(|t| t.name()) {
+            let location = last_locations.get(train.name()).unwrap_or(train.location());
+            match train.end_station() {
+                Some(end_station) if end_station != location => violations.push(format!(
+                    "Train {} ends at {location}, but its end station is {end_station}",
+                    train.name()
+                )),
+                _ => {}
+            }
+        }
+
+        for edge in self.edges {
+            let (Some(capacity), Some(usage)) = (edge.capacity(), edge_usage.get(edge.name()))
+            else {
+                continue;
+            };
+
+            if let Some((time, trains)) = congestion(usage, capacity) {
+                violations.push(format!(
+                    "Edge {} is used by {trains} trains at {time}, but its capacity is {capacity}",
+                    edge.name()
+                ));
+            }
+        }
+
+        for station in self.input.stations() {
+            let (Some(capacity), Some(usage)) = (
+                self.input.station_capacity(station),
+                station_usage.get(station),
+            ) else {
+                continue;
+            };
+
+            if let Some((time, trains)) = congestion(usage, capacity) {
+                violations.push(format!(
+                    "Station {station} has {trains} trains at {time}, but its capacity is {capacity}"
+                ));
+            }
+        }
+
+        for order in solution.unserviceable() {
+            match self.orders.get(order.as_str()) {
+                None => violations.push(format!("Unknown order `{order}`")),
+                Some(details) if self.is_serviceable(details) => violations.push(format!(
+                    "Order {order} is reported unserviceable, but a train can deliver it"
+                )),
+                _ => {}
+            }
+        }
+
+        for order in self.orders.values().sorted_by_key(|o| o.name()) {
+            match dropped_at.get(order.name()) {
+                None if !order.is_delivered()
+                    && !solution.unserviceable().iter().any(|o| o == order.name()) =>
+                {
+                    violations.push(format!("Order {} is not delivered", order.name()))
+                }
+                Some((station, _)) if station != &order.destination() => violations.push(format!(
+                    "Order {} is dropped at {station}, but its destination is {}",
+                    order.name(),
+                    order.destination()
+                )),
+                Some((_, time)) => {
+                    let reported = solution.late().iter().any(|o| o == order.name());
+                    match (order.is_late(*time), reported) {
+                        (true, false) => violations.push(format!(
+                            "Order {} is delivered at {time}, after its latest delivery {}",
+                            order.name(),
+                            order.latest_delivery().unwrap_or_default()
+                        )),
+                        (false, true) => violations.push(format!(
+                            "Order {} is reported late, but it is delivered at {time}",
+                            order.name()
+                        )),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if makespan != solution.total_time() {
+            violations.push(format!(
+                "Total time is {}, but the last train arrives at {makespan}",
+                solution.total_time()
+            ));
+        }
+
+        violations
+    }
+
+    fn arrival(&self, train: &Train, m: &Move) -> Option<u32> {
+        self.network
+            .distance(&m.from().into(), &m.to().into())
+            .map(|distance| m.time() + train.travel_time(distance))
+    }
+
+    /// Total time to load or unload known `orders`.
+    fn handling_time(&self, orders: &[&str]) -> u32 {
+        orders
+            .iter()
+            .filter_map(|o| self.orders.get(o))
+            .map(|o| o.handling_time())
+            .sum()
+    }
+
+    /// Some train can get to the order from where it starts, and then to the order destination.
+    fn is_serviceable(&self, order: &Order) -> bool {
+        self.network
+            .distance(&order.location(), &order.destination())
+            .is_some()
+            && self.trains.values().any(|train| {
+                self.network
+                    .distance(train.location(), &order.location())
+                    .is_some()
+            })
+    }
+
+    fn check_stations(&self, m: &Move) -> Result<(), String> {
+        for station in [m.from(), m.to()] {
+            if !self.stations.contains(&Station::from(station)) {
+                return Err(format!(
+                    "Train {} moves at {} via unknown station `{station}`",
+                    m.train(),
+                    m.time()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The first time an edge is used by more trains than `capacity`, and the number of trains then.
+fn congestion(usage: &[Interval], capacity: u32) -> Option<(u32, u32)> {
+    let mut trains = 0;
+
+    // Trains leaving the edge make room before others enter it at the same time.
+    for (time, change) in usage
+        .iter()
+        .filter(|(start, end)| start < end)
+        .flat_map(|(start, end)| [(*start, 1), (*end, -1)])
+        .sorted()
+    {
+        trains += change;
+        if trains > capacity as i32 {
+            return Some((time, trains as u32));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::Move;
+    use crate::{solve, verify, Input, Solution};
+
+    static SIMPLE_INPUT: &str = include_str!("data/simple.txt");
+
+    fn mv(time: u32, from: &str, load: &[&str], to: &str) -> Move {
+        let load = load.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+        Move::new(
+            time,
+            "Q1".to_owned(),
+            from.to_owned(),
+            load.clone(),
+            to.to_owned(),
+            load,
+        )
+    }
+
+    fn violations(moves: Vec<Move>, total_time: u32) -> String {
+        let input = Input::try_from(SIMPLE_INPUT).expect("Test input");
+        format!(
+            "{:#}",
+            verify(&input, &Solution::new(moves, total_time)).expect_err("Invalid solution")
+        )
+    }
+
+    #[test]
+    fn test_verify_solutions() {
+        for data in [
+            SIMPLE_INPUT,
+            include_str!("data/generated.small.1.txt"),
+            include_str!("data/generated.small.multiload.txt"),
+            include_str!("data/generated.large.1.txt"),
+            include_str!("data/one_way.txt"),
+            include_str!("data/time_windows.txt"),
+            include_str!("data/service_times.txt"),
+            include_str!("data/home_depot.txt"),
+            include_str!("data/single_track.txt"),
+            include_str!("data/station_capacity.txt"),
+        ] {
+            let input = Input::try_from(data).expect("Test input");
+            let solution = solve(&input).expect("Solve input");
+            verify(&input, &solution).expect("Valid solution");
+        }
+    }
+
+    #[test]
+    fn test_detect_wrong_departure_station() {
+        assert_eq!(
+            violations(vec![mv(0, "A", &["K1"], "C")], 40),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Train Q1 departs from A at 0, but it is at B"}
+        );
+    }
+
+    #[test]
+    fn test_detect_overlapping_moves() {
+        assert_eq!(
+            violations(vec![mv(0, "B", &[], "A"), mv(20, "A", &["K1"], "C")], 60),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Train Q1 departs at 20, but it arrives to A at 30"}
+        );
+    }
+
+    #[test]
+    fn test_detect_undelivered_orders() {
+        assert_eq!(
+            violations(vec![mv(0, "B", &[], "A"), mv(30, "A", &["K1"], "B")], 60),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Order K1 is dropped at B, but its destination is C"}
+        );
+        assert_eq!(
+            violations(vec![mv(0, "B", &[], "C")], 10),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Order K1 is not delivered"}
+        );
+    }
+
+    #[test]
+    fn test_detect_wrong_pickup_and_total_time() {
+        assert_eq!(
+            violations(vec![mv(0, "B", &["K1"], "C")], 70),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Order K1 is picked up at B, but it is at A
+                  - Total time is 70, but the last train arrives at 10"}
+        );
+    }
+
+    #[test]
+    fn test_detect_time_window_violations() {
+        let input = Input::try_from(include_str!("data/time_windows.txt")).expect("Test input");
+        let order = |time, train: &str, from: &str, order: &str, to: &str| {
+            let load = vec![order.to_owned()];
+            Move::new(
+                time,
+                train.to_owned(),
+                from.to_owned(),
+                load.clone(),
+                to.to_owned(),
+                load,
+            )
+        };
+        let solution = Solution::new(
+            vec![
+                order(0, "Q1", "A", "K1", "C"),
+                order(20, "Q1", "C", "K4", "A"),
+                order(0, "Q2", "D", "K3", "B"),
+                order(20, "Q2", "B", "K2", "D"),
+            ],
+            40,
+        )
+        .with_late(vec!["K3".to_owned()]);
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Order K4 is picked up at 20, before its earliest pickup 50
+                  - Order K2 is picked up at 20, before its earliest pickup 30
+                  - Order K3 is reported late, but it is delivered at 20"}
+        );
+    }
+
+    #[test]
+    fn test_detect_handling_violations() {
+        let input = Input::try_from(include_str!("data/service_times.txt")).expect("Test input");
+        let order = |time, train: &str, from: &str, orders: &[&str], to: &str| {
+            let load = orders.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+            Move::new(
+                time,
+                train.to_owned(),
+                from.to_owned(),
+                load.clone(),
+                to.to_owned(),
+                load,
+            )
+        };
+        // Q2 leaves before it loads K3 at C and unloads it at B.
+        let solution = Solution::new(
+            vec![
+                order(0, "Q2", "C", &["K3"], "B"),
+                order(30, "Q1", "A", &["K1"], "B"),
+                order(15, "Q2", "B", &[], "A"),
+                order(45, "Q2", "A", &["K2"], "C"),
+            ],
+            70,
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Train Q2 departs at 0, but it handles orders at C until 10
+                  - Train Q2 departs at 15, but it handles orders at B until 20"}
+        );
+    }
+
+    #[test]
+    fn test_detect_edge_conflicts() {
+        let input = Input::try_from(include_str!("data/single_track.txt")).expect("Test input");
+        let solution = Solution::new(
+            vec![
+                Move::new(
+                    0,
+                    "Q1".to_owned(),
+                    "A".to_owned(),
+                    vec!["K1".to_owned()],
+                    "C".to_owned(),
+                    vec!["K1".to_owned()],
+                ),
+                Move::new(
+                    0,
+                    "Q2".to_owned(),
+                    "C".to_owned(),
+                    vec!["K2".to_owned()],
+                    "A".to_owned(),
+                    vec!["K2".to_owned()],
+                ),
+            ],
+            30,
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Edge E2 is used by 2 trains at 10, but its capacity is 1"}
+        );
+    }
+
+    #[test]
+    fn test_detect_full_stations() {
+        let input = Input::try_from(include_str!("data/station_capacity.txt")).expect("Test input");
+        let order = |time, train: &str, from: &str, order: &str, to: &str| {
+            let load = vec![order.to_owned()];
+            Move::new(
+                time,
+                train.to_owned(),
+                from.to_owned(),
+                load.clone(),
+                to.to_owned(),
+                load,
+            )
+        };
+        let solution = Solution::new(
+            vec![
+                order(0, "Q1", "A", "K1", "B"),
+                order(15, "Q1", "B", "K1", "C"),
+                order(0, "Q2", "C", "K2", "B"),
+                order(12, "Q2", "B", "K2", "A"),
+            ],
+            25,
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Station B has 2 trains at 10, but its capacity is 1"}
+        );
+    }
+
+    #[test]
+    fn test_detect_full_stations_with_idle_trains() {
+        let input = Input::try_from(indoc::indoc! {"
+            3
+            A
+            B,1
+            C
+
+            2
+            E1,A,B,10
+            E2,B,C,10
+
+            1
+            K1,1,A,C
+
+            2
+            Q1,1,A
+            Q2,1,B
+        "})
+        .expect("Test input");
+        // Q2 has nothing to do, and it stays at B while Q1 waits there.
+        let solution = Solution::new(
+            vec![mv(0, "A", &["K1"], "B"), mv(15, "B", &["K1"], "C")],
+            25,
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Station B has 2 trains at 10, but its capacity is 1"}
+        );
+    }
+
+    #[test]
+    fn test_detect_trains_away_from_end_station() {
+        let input = Input::try_from(include_str!("data/home_depot.txt")).expect("Test input");
+        let solution = Solution::new(
+            vec![
+                Move::new(
+                    0,
+                    "Q1".to_owned(),
+                    "B".to_owned(),
+                    vec!["K1".to_owned()],
+                    "D".to_owned(),
+                    vec!["K1".to_owned()],
+                ),
+                Move::new(
+                    0,
+                    "Q2".to_owned(),
+                    "C".to_owned(),
+                    vec![],
+                    "A".to_owned(),
+                    vec![],
+                ),
+                Move::new(
+                    20,
+                    "Q2".to_owned(),
+                    "A".to_owned(),
+                    vec!["K2".to_owned()],
+                    "C".to_owned(),
+                    vec!["K2".to_owned()],
+                ),
+            ],
+            40,
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Train Q1 ends at D, but its end station is B
+                  - Train Q2 ends at C, but its end station is D"}
+        );
+    }
+
+    #[test]
+    fn test_detect_false_unserviceable_orders() {
+        let input = Input::try_from(SIMPLE_INPUT).expect("Test input");
+        let solution = Solution::new(vec![], 0).with_unserviceable(vec!["K1".to_owned()]);
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Order K1 is reported unserviceable, but a train can deliver it"}
+        );
+    }
+
+    #[test]
+    fn test_detect_unreachable_move() {
+        let input = Input::try_from(include_str!("data/disconnected.txt")).expect("Test input");
+        let solution = Solution::new(
+            vec![Move::new(
+                0,
+                "Q1".to_owned(),
+                "B".to_owned(),
+                vec![],
+                "D".to_owned(),
+                vec![],
+            )],
+            0,
+        )
+        .with_unserviceable(vec!["K2".to_owned(), "K3".to_owned()]);
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                verify(&input, &solution).expect_err("Invalid solution")
+            ),
+            indoc::indoc! {"
+                Solution is invalid:
+                  - Train Q1 can't reach D from B at 0
+                  - Order K1 is not delivered
+                  - Order K4 is not delivered"}
+        );
+    }
+
+    #[test]
+    fn test_detect_overload() {
+        let input =
+            Input::try_from(include_str!("data/generated.small.overload.txt")).expect("Test input");
+        let orders = input
+            .orders()
+            .iter()
+            .filter(|o| o.location() == "N0".into())
+            .map(|o| o.name().to_owned())
+            .collect::<Vec<_>>();
+        let train = &input.trains()[0];
+
+        let solution = Solution::new(
+            vec![
+                Move::new(
+                    0,
+                    train.name().to_owned(),
+                    train.location().name().to_owned(),
+                    vec![],
+                    "N0".to_owned(),
+                    vec![],
+                ),
+                Move::new(
+                    1,
+                    train.name().to_owned(),
+                    "N0".to_owned(),
+                    orders.clone(),
+                    "N1".to_owned(),
+                    orders,
+                ),
+            ],
+            2,
+        );
+
+        let error = format!("{:#}", verify(&input, &solution).expect_err("Overload"));
+        assert!(
+            error.contains("on board, but its capacity is"),
+            "Unexpected error: {error}"
+        );
+    }
+}